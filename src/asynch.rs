@@ -0,0 +1,166 @@
+//! `async` versions of the transmit and receive functions, woken up by the RADIO interrupt
+//!
+//! For these to work, the `RADIO` interrupt has to be unmasked in the NVIC, and its handler has
+//! to call [`crate::on_interrupt`] - or [`crate::on_interrupt_with`], for registers other than
+//! the nRF51's own. A [`critical_section`] implementation is needed as well - on the nRF51, the
+//! one from the `cortex-m` crate (`critical-section-single-core` feature) will do.
+
+use core::{cell::Cell, future::poll_fn, task::Poll, task::Waker};
+
+use critical_section::Mutex;
+
+use crate::{
+    Enabled, Event, Interrupt, Radio, RadioRegisters, Receiver, State, Transmitter, packet,
+    reg_access,
+};
+
+/// The waker of the future that is waiting for the radio
+static WAKER: Mutex<Cell<Option<Waker>>> = Mutex::new(Cell::new(None));
+/// Interrupts that fired since the future last checked, as [`crate::on_interrupt_with`] clears
+/// their events
+static FIRED: Mutex<Cell<u32>> = Mutex::new(Cell::new(0));
+
+/// Records the interrupts that fired and wakes the waiting future. Called from
+/// [`crate::on_interrupt_with`]
+pub(crate) fn on_interrupt(fired: crate::Interrupts) {
+    if fired.is_empty() {
+        return;
+    }
+
+    critical_section::with(|cs| {
+        let f = FIRED.borrow(cs);
+        f.set(f.get() | fired.bits());
+
+        if let Some(waker) = WAKER.borrow(cs).take() {
+            waker.wake();
+        }
+    });
+}
+
+/// Takes `interrupt` out of the set of fired interrupts, returning whether it was there
+fn take_fired(interrupt: Interrupt) -> bool {
+    critical_section::with(|cs| {
+        let f = FIRED.borrow(cs);
+        let fired = f.get() & interrupt as u32 != 0;
+        f.set(f.get() & !(interrupt as u32));
+
+        fired
+    })
+}
+
+/// Stops the radio if the future owning this is dropped before the operation completes, so that
+/// it doesn't keep accessing a buffer which doesn't exist anymore
+struct CancelOnDrop<'a, R: RadioRegisters> {
+    radio: &'a R,
+    enable: fn(&R),
+    idle: State,
+}
+
+impl<R: RadioRegisters> CancelOnDrop<'_, R> {
+    /// The operation completed, so there's nothing to cancel
+    fn defuse(self) {
+        core::mem::forget(self);
+    }
+}
+
+impl<R: RadioRegisters> Drop for CancelOnDrop<'_, R> {
+    fn drop(&mut self) {
+        reg_access::disable_interrupt(self.radio, Interrupt::End);
+
+        reg_access::disable(self.radio);
+        while reg_access::get_state(self.radio) != Some(State::DISABLED) {
+            core::hint::spin_loop();
+        }
+
+        // ramp the radio up again, so that it is in the state its type says it is
+        (self.enable)(self.radio);
+        while reg_access::get_state(self.radio) != Some(self.idle) {
+            core::hint::spin_loop();
+        }
+    }
+}
+
+impl<T, R: RadioRegisters> Radio<Enabled<T>, R> {
+    /// Waits until `interrupt` fires, clearing its event
+    async fn wait_for_interrupt(&self, interrupt: Interrupt) {
+        poll_fn(|cx| {
+            critical_section::with(|cs| WAKER.borrow(cs).set(Some(cx.waker().clone())));
+
+            // the event may have been raised before the waker got registered
+            if take_fired(interrupt) || self.event_occurred(interrupt.event()) {
+                self.clear_event(interrupt.event());
+                return Poll::Ready(());
+            }
+
+            Poll::Pending
+        })
+        .await;
+
+        reg_access::disable_interrupt(&self.radio, interrupt);
+    }
+
+    /// Prepares `interrupt` to be waited on with [`Self::wait_for_interrupt`]
+    fn arm_interrupt(&self, interrupt: Interrupt) {
+        take_fired(interrupt);
+        self.clear_event(interrupt.event());
+        reg_access::enable_interrupt(&self.radio, interrupt);
+    }
+}
+
+impl<R: RadioRegisters> Radio<Enabled<Transmitter>, R> {
+    /// Transmits a packet, waiting for the `END` interrupt
+    ///
+    /// If the future is dropped before the transmission completes, the radio is disabled and
+    /// ramped up again, so it's back in [`State::TX_IDLE`]. The radio is borrowed mutably, as
+    /// there's only one waker for the interrupt, so only one operation can be in flight.
+    pub async fn transmit(&mut self, packet: &packet::Packet) -> crate::Result<()> {
+        let mut serialized = self.serialize_for_tx(packet)?;
+        reg_access::set_packet_ptr(&self.radio, serialized.buf_mut().as_mut_ptr());
+
+        // declared after `serialized`, so it's dropped before it
+        let guard = CancelOnDrop {
+            radio: &self.radio,
+            enable: reg_access::enable_tx,
+            idle: State::TX_IDLE,
+        };
+
+        self.arm_interrupt(Interrupt::End);
+        reg_access::tasks::start(&self.radio);
+
+        self.wait_for_interrupt(Interrupt::End).await;
+        guard.defuse();
+
+        Ok(())
+    }
+}
+
+impl<R: RadioRegisters> Radio<Enabled<Receiver>, R> {
+    /// Receives a packet, waiting for the `END` interrupt
+    ///
+    /// The RSSI is only sampled if [`crate::Shortcuts::ADDRESS_RSSISTART`] is enabled, and the
+    /// packet isn't timestamped. Apart from that, this behaves like
+    /// [`Radio::receive_packet`]. If the future is dropped before a packet is received, the
+    /// radio is disabled and ramped up again, so it's back in [`State::RX_IDLE`]. Like
+    /// [`Radio::transmit`], it borrows the radio mutably.
+    pub async fn receive(&mut self) -> crate::Result<packet::ReceivedPacket> {
+        let mut p = self.new_rx_packet()?;
+        reg_access::set_packet_ptr(&self.radio, p.buf_mut_ptr());
+
+        // declared after `p`, so it's dropped before it
+        let guard = CancelOnDrop {
+            radio: &self.radio,
+            enable: reg_access::enable_rx,
+            idle: State::RX_IDLE,
+        };
+
+        self.clear_event(Event::RSSIEnd);
+        self.clear_event(Event::DevMatch);
+        self.arm_interrupt(Interrupt::End);
+        reg_access::tasks::start(&self.radio);
+
+        self.wait_for_interrupt(Interrupt::End).await;
+        guard.defuse();
+
+        self.finish_rx_packet(p, None)
+    }
+}
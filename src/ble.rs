@@ -0,0 +1,73 @@
+//! Bluetooth Low Energy
+//!
+//! Only the parts which don't need a connection are covered. Packets on the advertising channels
+//! use the layout and CRC of [`crate::preset::Preset::Ble1M`], the
+//! [`ADVERTISING_ACCESS_ADDRESS`] and a whitening IV which is the channel index, all of which
+//! [`Radio::set_advertising_channel`] and [`Radio::set_advertising_access_address`] take care of
+//! on top of the preset.
+
+pub mod advertiser;
+
+use crate::{Address, Enabled, Frequency, PhysicalAddress, Radio, RadioRegisters, reg_access};
+
+/// The access address of all packets on the advertising channels
+pub const ADVERTISING_ACCESS_ADDRESS: u32 = 0x8E89_BED6;
+
+/// One of the three primary advertising channels
+#[derive(Copy, Clone, Eq, PartialEq, Debug, strum::EnumIter)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AdvertisingChannel {
+    /// Channel 37, on 2402 MHz
+    Ch37,
+    /// Channel 38, on 2426 MHz
+    Ch38,
+    /// Channel 39, on 2480 MHz
+    Ch39,
+}
+
+impl AdvertisingChannel {
+    /// The channel index, which is also the whitening IV
+    pub fn index(&self) -> u8 {
+        match self {
+            Self::Ch37 => 37,
+            Self::Ch38 => 38,
+            Self::Ch39 => 39,
+        }
+    }
+
+    /// The frequency of the channel
+    pub fn frequency(&self) -> Frequency {
+        match self {
+            Self::Ch37 => Frequency(2),
+            Self::Ch38 => Frequency(26),
+            Self::Ch39 => Frequency(80),
+        }
+    }
+}
+
+impl<T, R: RadioRegisters> Radio<Enabled<T>, R> {
+    /// Set the frequency and whitening IV of an advertising channel
+    pub fn set_advertising_channel(&self, channel: AdvertisingChannel) -> &Self {
+        reg_access::write_whitening_iv(&self.radio, channel.index());
+
+        self.set_frequency(channel.frequency())
+    }
+
+    /// Set the on-air address of `address` to the [`ADVERTISING_ACCESS_ADDRESS`]
+    ///
+    /// Returns [`crate::Error::ValueOutOfBounds`] if the radio isn't set to a 3 byte base
+    /// address, which the preset is.
+    pub fn set_advertising_access_address(&self, address: Address) -> crate::Result<&Self> {
+        if self.base_address_length().map(|l| l.bytes()) != Some(3) {
+            return Err(crate::Error::ValueOutOfBounds);
+        }
+
+        self.set_physical_address(
+            address,
+            PhysicalAddress {
+                base: ADVERTISING_ACCESS_ADDRESS & 0xFF_FFFF,
+                prefix: (ADVERTISING_ACCESS_ADDRESS >> 24) as u8,
+            },
+        )
+    }
+}
@@ -0,0 +1,176 @@
+//! Non-connectable advertising, as beacons do it
+//!
+//! An [`Advertiser`] sends the same PDU on channels 37, 38 and 39, one right after the other -
+//! an advertising event - once every advertising interval. Each interval is extended by a random
+//! delay of up to [`MAX_ADV_DELAY`] (`advDelay`), so that advertisers which happen to share an
+//! interval don't keep colliding.
+//!
+//! The PDUs don't offer a connection: [`PduType::NonConnectable`] (`ADV_NONCONN_IND`) is a pure
+//! broadcast, while [`PduType::Scannable`] (`ADV_SCAN_IND`) tells scanners they may ask for
+//! more, although this advertiser doesn't listen for scan requests.
+
+use core::time::Duration;
+
+use strum::IntoEnumIterator;
+
+use crate::{
+    Address, DeviceAddress, Enabled, Radio, RadioRegisters, Transmitter, ble::AdvertisingChannel,
+    clock::Clock, packet, preset::Preset,
+};
+
+/// Maximum length of the advertising data
+pub const MAX_DATA_LENGTH: usize = 31;
+
+/// Shortest advertising interval allowed for non-connectable advertising
+pub const MIN_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Longest advertising interval
+pub const MAX_INTERVAL: Duration = Duration::from_millis(10_240);
+
+/// Longest random delay added to each advertising interval
+pub const MAX_ADV_DELAY: Duration = Duration::from_millis(10);
+
+/// Length of the advertiser's address, which the payload starts with
+const ADDRESS_LENGTH: usize = 6;
+
+/// The kind of advertising PDU to send
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PduType {
+    /// `ADV_NONCONN_IND`: neither connectable nor scannable
+    NonConnectable,
+    /// `ADV_SCAN_IND`: scannable, but not connectable
+    Scannable,
+}
+
+impl PduType {
+    /// The value of the `PDU Type` bits of the header
+    pub fn bits(&self) -> u8 {
+        match self {
+            Self::NonConnectable => 0b0010,
+            Self::Scannable => 0b0110,
+        }
+    }
+}
+
+/// Sends advertising events. See the [module documentation](self)
+pub struct Advertiser<R: RadioRegisters = nrf51_pac::RADIO> {
+    radio: Radio<Enabled<Transmitter>, R>,
+    address: DeviceAddress,
+    pdu_type: PduType,
+    packet: packet::Packet,
+    interval: Duration,
+    /// When the next advertising event is due, in microseconds
+    next: Option<u64>,
+}
+
+impl<R: RadioRegisters> Advertiser<R> {
+    /// Takes over `radio`, setting it up for the advertising channels, to advertise `data` from
+    /// `address` every `interval`. The [`DeviceAddress::tx_add`] bit says whether the address is
+    /// a random one
+    ///
+    /// Returns [`crate::Error::ValueOutOfBounds`] if the data is longer than
+    /// [`MAX_DATA_LENGTH`], or if the interval isn't between [`MIN_INTERVAL`] and
+    /// [`MAX_INTERVAL`].
+    pub fn new(
+        radio: Radio<Enabled<Transmitter>, R>,
+        address: DeviceAddress,
+        pdu_type: PduType,
+        data: &[u8],
+        interval: Duration,
+    ) -> crate::Result<Self> {
+        if !(MIN_INTERVAL..=MAX_INTERVAL).contains(&interval) {
+            return Err(crate::Error::ValueOutOfBounds);
+        }
+
+        radio
+            .apply_preset(Preset::Ble1M)?
+            .set_advertising_access_address(Address::A)?;
+        radio.set_tx_address(Address::A);
+
+        let packet = pdu(&radio, address, pdu_type, data)?;
+
+        Ok(Self {
+            radio,
+            address,
+            pdu_type,
+            packet,
+            interval,
+            next: None,
+        })
+    }
+
+    /// Gives back the radio
+    pub fn free(self) -> Radio<Enabled<Transmitter>, R> {
+        self.radio
+    }
+
+    /// Replace the advertising data, starting with the next event
+    ///
+    /// Returns [`crate::Error::ValueOutOfBounds`] if the data is longer than
+    /// [`MAX_DATA_LENGTH`].
+    pub fn set_data(&mut self, data: &[u8]) -> crate::Result<()> {
+        self.packet = pdu(&self.radio, self.address, self.pdu_type, data)?;
+
+        Ok(())
+    }
+
+    /// Sends one advertising event right away, on channels 37, 38 and 39 in that order
+    pub fn advertise_event(&self) -> crate::Result<()> {
+        for channel in AdvertisingChannel::iter() {
+            self.radio.set_advertising_channel(channel);
+            self.radio.transmit_packet(&self.packet)?;
+        }
+
+        Ok(())
+    }
+
+    /// Waits until the next advertising event is due and sends it. The first one is sent right
+    /// away
+    ///
+    /// Time is measured with `clock`. `random` is called once per event to draw the delay added
+    /// to the interval, so it should return uniformly distributed values - the RNG peripheral is
+    /// a good source.
+    pub fn advertise(
+        &mut self,
+        clock: &mut impl Clock,
+        random: &mut impl FnMut() -> u32,
+    ) -> crate::Result<()> {
+        if let Some(next) = self.next {
+            while clock.now_micros() < next {
+                core::hint::spin_loop();
+            }
+        }
+
+        let start = clock.now_micros();
+        self.advertise_event()?;
+
+        let delay = u64::from(random()) % (MAX_ADV_DELAY.as_micros() as u64 + 1);
+        self.next = Some(start + self.interval.as_micros() as u64 + delay);
+
+        Ok(())
+    }
+}
+
+/// Builds the advertising PDU: the header, followed by the advertiser's address and `data`
+fn pdu<R: RadioRegisters>(
+    radio: &Radio<Enabled<Transmitter>, R>,
+    address: DeviceAddress,
+    pdu_type: PduType,
+    data: &[u8],
+) -> crate::Result<packet::Packet> {
+    if data.len() > MAX_DATA_LENGTH {
+        return Err(crate::Error::ValueOutOfBounds);
+    }
+
+    let mut payload = [0; ADDRESS_LENGTH + MAX_DATA_LENGTH];
+    payload[..4].copy_from_slice(&address.base.to_le_bytes());
+    payload[4..ADDRESS_LENGTH].copy_from_slice(&address.prefix.to_le_bytes());
+    payload[ADDRESS_LENGTH..][..data.len()].copy_from_slice(data);
+
+    radio
+        .packet_builder()
+        .s0(pdu_type.bits() | (u8::from(address.tx_add) << 6))
+        .payload(&payload[..ADDRESS_LENGTH + data.len()])
+        .build()
+}
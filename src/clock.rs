@@ -0,0 +1,170 @@
+//! Sources of time for the `_within` timeouts of [`crate::Radio`]
+//!
+//! Timeouts given in CPU cycles (like [`crate::Radio::receive_packet_with_timeout`]) actually
+//! count loop iterations, so how long they take depends on the compiler and flash wait states.
+//! The functions taking a [`core::time::Duration`] measure it with a [`Clock`] instead. How
+//! accurate the timeout is depends on the clock:
+//!
+//! - [`TimerClock`] counts microseconds with a TIMER. A timeout ends at most one loop iteration
+//!   (a few microseconds) after it expires.
+//! - [`RtcClock`] counts ticks of the 32.768 kHz RTC, so a timeout ends up to one tick
+//!   (~30.5 µs) late, on top of the loop iteration.
+//! - [`DelayClock`] only counts the time it spends in [`DelayNs::delay_us`], and not the time
+//!   spent polling the radio in between, so timeouts take longer than requested. The error grows
+//!   with the number of polls, so it's best used with a step that is long compared to a poll.
+//!
+//! Any `FnMut() -> u64` returning microseconds is a [`Clock`] too, for example a wrapper around
+//! the monotonic clock of an RTOS.
+
+use core::ops::Deref;
+
+use embedded_hal::delay::DelayNs;
+use nrf51_pac::{rtc0, timer0};
+
+/// A monotonic clock counting microseconds
+pub trait Clock {
+    /// Microseconds since an arbitrary point in time
+    fn now_micros(&mut self) -> u64;
+}
+
+impl<F: FnMut() -> u64> Clock for F {
+    fn now_micros(&mut self) -> u64 {
+        self()
+    }
+}
+
+/// Extends a counter that is `bits` wide, which has been read as `now`, to 64 bits
+fn extend(total: &mut u64, last: &mut u32, now: u32, bits: u32) {
+    let mask = (1u64 << bits) - 1;
+
+    *total += u64::from(now.wrapping_sub(*last)) & mask;
+    *last = now;
+}
+
+/// A [`Clock`] backed by one of the TIMER peripherals, running at 1 MHz
+///
+/// The timer runs in 16-bit mode, which all nRF51 TIMERs support, and is extended to 64 bits in
+/// software. It overflows every 65.5 ms, so the clock has to be read at least that often to
+/// stay accurate - which polling the radio does. The 16 MHz high frequency clock has to be
+/// running.
+pub struct TimerClock<T> {
+    timer: T,
+    last: u32,
+    total: u64,
+}
+
+impl<T: Deref<Target = timer0::RegisterBlock>> TimerClock<T> {
+    /// Takes over `timer`, configuring it as a 16-bit 1 MHz timer and starting it
+    pub fn new(timer: T) -> Self {
+        timer.tasks_stop.write(|w| unsafe { w.bits(1) });
+        timer.mode.write(|w| w.mode().timer());
+        timer.bitmode.write(|w| w.bitmode()._16bit());
+        // 16 MHz / 2^4
+        timer.prescaler.write(|w| unsafe { w.prescaler().bits(4) });
+        timer.tasks_clear.write(|w| unsafe { w.bits(1) });
+        timer.tasks_start.write(|w| unsafe { w.bits(1) });
+
+        Self {
+            timer,
+            last: 0,
+            total: 0,
+        }
+    }
+
+    /// Stops the timer and gives it back
+    pub fn free(self) -> T {
+        self.timer.tasks_stop.write(|w| unsafe { w.bits(1) });
+
+        self.timer
+    }
+}
+
+impl<T: Deref<Target = timer0::RegisterBlock>> Clock for TimerClock<T> {
+    fn now_micros(&mut self) -> u64 {
+        self.timer.tasks_capture[0].write(|w| unsafe { w.bits(1) });
+        let now = self.timer.cc[0].read().bits();
+
+        extend(&mut self.total, &mut self.last, now, 16);
+        self.total
+    }
+}
+
+/// Frequency of the RTC with a prescaler of 0
+const RTC_FREQUENCY: u64 = 32_768;
+
+/// A [`Clock`] backed by one of the RTC peripherals, running at 32.768 kHz
+///
+/// The 24-bit counter is extended to 64 bits in software. It overflows every 512 seconds, so
+/// the clock has to be read at least that often. The low frequency clock has to be running.
+pub struct RtcClock<T> {
+    rtc: T,
+    last: u32,
+    ticks: u64,
+}
+
+impl<T: Deref<Target = rtc0::RegisterBlock>> RtcClock<T> {
+    /// Takes over `rtc`, setting its prescaler to 0 and starting it
+    pub fn new(rtc: T) -> Self {
+        rtc.tasks_stop.write(|w| unsafe { w.bits(1) });
+        rtc.prescaler.write(|w| unsafe { w.prescaler().bits(0) });
+        rtc.tasks_clear.write(|w| unsafe { w.bits(1) });
+        rtc.tasks_start.write(|w| unsafe { w.bits(1) });
+
+        Self {
+            rtc,
+            last: 0,
+            ticks: 0,
+        }
+    }
+
+    /// Stops the RTC and gives it back
+    pub fn free(self) -> T {
+        self.rtc.tasks_stop.write(|w| unsafe { w.bits(1) });
+
+        self.rtc
+    }
+}
+
+impl<T: Deref<Target = rtc0::RegisterBlock>> Clock for RtcClock<T> {
+    fn now_micros(&mut self) -> u64 {
+        let now = self.rtc.counter.read().counter().bits();
+
+        extend(&mut self.ticks, &mut self.last, now, 24);
+        self.ticks * 1_000_000 / RTC_FREQUENCY
+    }
+}
+
+/// A [`Clock`] which advances by sleeping with a [`DelayNs`] implementation
+///
+/// Every reading of the clock delays for `step_us` microseconds first, and adds them to the
+/// time. See the [module documentation](self) for how this affects accuracy.
+pub struct DelayClock<D> {
+    delay: D,
+    step_us: u32,
+    elapsed: u64,
+}
+
+impl<D: DelayNs> DelayClock<D> {
+    /// Constructs a clock advancing by `step_us` microseconds every time it's read
+    pub fn new(delay: D, step_us: u32) -> Self {
+        Self {
+            delay,
+            step_us,
+            elapsed: 0,
+        }
+    }
+
+    /// Gives back the delay
+    pub fn free(self) -> D {
+        self.delay
+    }
+}
+
+impl<D: DelayNs> Clock for DelayClock<D> {
+    fn now_micros(&mut self) -> u64 {
+        self.delay.delay_us(self.step_us);
+        self.elapsed += u64::from(self.step_us);
+
+        self.elapsed
+    }
+}
@@ -0,0 +1,102 @@
+//! Integration with [embassy](https://embassy.dev)
+//!
+//! The RADIO interrupt is bound with [`crate::bind_interrupts`], which works just like the macro
+//! of the same name from the embassy HALs:
+//!
+//! ```ignore
+//! nrf51_radio::bind_interrupts!(struct Irqs {
+//!     RADIO => nrf51_radio::embassy::InterruptHandler;
+//! });
+//!
+//! let mut radio = Radio::new_bound(p.RADIO, Irqs).into_transmitter();
+//! radio.transmit_with_timeout(&packet, Duration::from_millis(10)).await?;
+//! ```
+//!
+//! [`Radio`] owns the peripheral, so it can be moved into an embassy task as it is.
+
+use embassy_time::{Duration, with_timeout};
+
+use crate::{Disabled, Enabled, Radio, RadioRegisters, Receiver, Transmitter, packet};
+
+/// Handler for the RADIO interrupt, to be bound with [`crate::bind_interrupts`]
+pub struct InterruptHandler {
+    _private: (),
+}
+
+impl InterruptHandler {
+    /// Handles the interrupt. Called from the handler generated by [`crate::bind_interrupts`]
+    #[doc(hidden)]
+    pub fn on_interrupt() {
+        crate::on_interrupt();
+    }
+}
+
+/// Proof that the RADIO interrupt is bound to the handler `H`
+///
+/// # Safety
+///
+/// This is implemented by [`crate::bind_interrupts`], and shouldn't be implemented manually.
+pub unsafe trait Binding<H> {}
+
+/// Binds the RADIO interrupt to [`InterruptHandler`], generating a struct implementing
+/// [`embassy::Binding`](crate::embassy::Binding)
+#[macro_export]
+macro_rules! bind_interrupts {
+    ($vis:vis struct $name:ident { RADIO => $handler:ty; }) => {
+        #[derive(Copy, Clone)]
+        $vis struct $name;
+
+        #[allow(non_snake_case)]
+        #[unsafe(no_mangle)]
+        unsafe extern "C" fn RADIO() {
+            <$handler>::on_interrupt();
+        }
+
+        // SAFETY: the handler is bound above
+        unsafe impl $crate::embassy::Binding<$handler> for $name {}
+    };
+}
+
+impl Radio<()> {
+    /// Like [`Self::new`], but also unmasks the RADIO interrupt in the NVIC, which is bound to
+    /// [`InterruptHandler`] by `_irqs`
+    pub fn new_bound(
+        radio: nrf51_pac::RADIO,
+        _irqs: impl Binding<InterruptHandler>,
+    ) -> Radio<Disabled> {
+        let radio = Self::new(radio);
+
+        cortex_m::peripheral::NVIC::unpend(nrf51_pac::Interrupt::RADIO);
+        // SAFETY: the interrupt is bound to our handler, so unmasking it can't break anything
+        unsafe { cortex_m::peripheral::NVIC::unmask(nrf51_pac::Interrupt::RADIO) };
+
+        radio
+    }
+}
+
+impl<R: RadioRegisters> Radio<Enabled<Transmitter>, R> {
+    /// Like [`Self::transmit`], but gives up after `timeout`, returning
+    /// [`crate::Error::TimedOut`]
+    pub async fn transmit_with_timeout(
+        &mut self,
+        packet: &packet::Packet,
+        timeout: Duration,
+    ) -> crate::Result<()> {
+        with_timeout(timeout, self.transmit(packet))
+            .await
+            .map_err(|_| crate::Error::TimedOut)?
+    }
+}
+
+impl<R: RadioRegisters> Radio<Enabled<Receiver>, R> {
+    /// Like [`Self::receive`], but gives up after `timeout`, returning
+    /// [`crate::Error::TimedOut`]
+    pub async fn receive_with_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> crate::Result<packet::ReceivedPacket> {
+        with_timeout(timeout, self.receive())
+            .await
+            .map_err(|_| crate::Error::TimedOut)?
+    }
+}
@@ -0,0 +1,499 @@
+//! Enhanced ShockBurst (ESB), the link layer of Nordic's nRF24 and nRF5 radios
+//!
+//! An ESB packet carries a 9-bit packet control field (PCF) between the address and the payload:
+//! a 6-bit payload length, which lives in the `LENGTH` field, followed by a 2-bit packet ID and a
+//! `NO_ACK` flag, which live in `S1` (see [`PacketControl`]). The radio has to be configured with
+//! one of the `EsbDynamic` presets, like [`crate::preset::Preset::EsbDynamic2M`].
+//!
+//! The primary transmitter ([`Ptx`]) sends packets and waits for the primary receiver ([`Prx`])
+//! to acknowledge them, retransmitting them if it doesn't. Each of the eight logical
+//! [`Address`]es is a pipe with its own packet IDs, which the receiver uses to tell a
+//! retransmission of a packet it has already acknowledged from a new one.
+//!
+//! Data can flow back to the transmitter too: payloads queued with [`Prx::queue_ack_payload`]
+//! are sent along with the acknowledgements on their pipe, and show up in [`Ack::payload`].
+
+use core::time::Duration;
+
+use crate::{
+    Address, Enabled, Frequency, Radio, RadioRegisters, Receiver, Transmitter, clock::Clock,
+    cycle_deadline, packet, reg_access, time_deadline,
+};
+
+/// Number of pipes, one per logical address
+pub const PIPES: usize = 8;
+
+/// Maximum length of the payload of an ESB packet
+pub const MAX_PAYLOAD_LENGTH: usize = 32;
+
+/// Packet IDs count up to this value and then wrap around to zero
+pub const MAX_PID: u8 = 3;
+
+/// How many ACK payloads can be queued on each pipe
+pub const ACK_QUEUE_DEPTH: usize = 3;
+
+/// How many CPU cycles sending an acknowledgement may take, plenty for the longest one at
+/// 250 kbit/s
+const ACK_CYCLES: u32 = 100_000;
+
+/// The part of the packet control field which lives in `S1`
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PacketControl {
+    /// The packet ID, which changes with every new packet but stays the same across
+    /// retransmissions. Only the lower two bits are used
+    pub pid: u8,
+    /// Whether the receiver should not acknowledge the packet
+    pub no_ack: bool,
+}
+
+impl PacketControl {
+    /// Decodes the value of the `S1` field
+    ///
+    /// The `NO_ACK` bit is set when an acknowledgement *is* wanted, as on Nordic's
+    /// implementations.
+    pub fn from_s1(s1: u16) -> Self {
+        Self {
+            pid: (s1 >> 1) as u8 & MAX_PID,
+            no_ack: s1 & 1 == 0,
+        }
+    }
+
+    /// Encodes the value of the `S1` field. See [`Self::from_s1`]
+    pub fn to_s1(&self) -> u16 {
+        (u16::from(self.pid & MAX_PID) << 1) | u16::from(!self.no_ack)
+    }
+}
+
+/// Checks that the packet layout is the one of ESB with dynamic payload length
+fn check_layout(config: Option<packet::PacketConfig>) -> crate::Result<()> {
+    match config {
+        Some(c) if c.s0_len.bytes() == 0 && c.lf_len.bits() == 6 && c.s1_len.bits() == 3 => Ok(()),
+        _ => Err(crate::Error::ValueOutOfBounds),
+    }
+}
+
+/// Retransmission settings of a [`Ptx`]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    /// How many times a packet is retransmitted if it isn't acknowledged
+    pub retransmits: u8,
+    /// How long to wait after an attempt wasn't acknowledged before retransmitting
+    #[cfg_attr(feature = "defmt", defmt(Debug2Format))]
+    pub retransmit_delay: Duration,
+    /// How long an attempt may take, from starting the transmission until the acknowledgement
+    /// has been received. It has to cover the packet itself, so it depends on the mode and the
+    /// length of the payload
+    #[cfg_attr(feature = "defmt", defmt(Debug2Format))]
+    pub ack_timeout: Duration,
+}
+
+impl Default for Config {
+    /// 3 retransmits, 600 µs apart, and 2 ms per attempt, which covers the longest packets at
+    /// 250 kbit/s
+    fn default() -> Self {
+        Self {
+            retransmits: 3,
+            retransmit_delay: Duration::from_micros(600),
+            ack_timeout: Duration::from_millis(2),
+        }
+    }
+}
+
+/// How a packet sent by [`Ptx::send`] was acknowledged
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Ack {
+    retransmits: u8,
+    packet: packet::ReceivedPacket,
+}
+
+impl Ack {
+    pub(crate) fn new(retransmits: u8, packet: packet::ReceivedPacket) -> Self {
+        Self {
+            retransmits,
+            packet,
+        }
+    }
+
+    /// How many times the packet had to be retransmitted
+    pub fn retransmits(&self) -> u8 {
+        self.retransmits
+    }
+
+    /// The signal strength of the acknowledgement in dBm, if it was sampled
+    pub fn rssi(&self) -> Option<i8> {
+        self.packet.rssi()
+    }
+
+    /// The payload the receiver sent along with the acknowledgement, which is empty if it had
+    /// nothing queued
+    pub fn payload(&self) -> &[u8] {
+        self.packet.packet().payload()
+    }
+}
+
+/// A primary transmitter. See the [module documentation](self)
+pub struct Ptx<R: RadioRegisters = nrf51_pac::RADIO> {
+    radio: Radio<Enabled<Transmitter>, R>,
+    config: Config,
+    pids: [u8; PIPES],
+}
+
+impl<R: RadioRegisters> Ptx<R> {
+    /// Takes over `radio`, which has to be set up for ESB with dynamic payload length
+    ///
+    /// Returns [`crate::Error::ValueOutOfBounds`] if the packet layout of the radio isn't the one
+    /// of ESB.
+    pub fn new(radio: Radio<Enabled<Transmitter>, R>, config: Config) -> crate::Result<Self> {
+        check_layout(radio.packet_config())?;
+
+        Ok(Self {
+            radio,
+            config,
+            pids: [0; PIPES],
+        })
+    }
+
+    /// Gives back the radio
+    pub fn free(self) -> Radio<Enabled<Transmitter>, R> {
+        self.radio
+    }
+
+    /// Set the frequency packets are sent on
+    pub fn set_frequency(&self, freq: Frequency) -> &Self {
+        self.radio.set_frequency(freq);
+
+        self
+    }
+
+    /// Sends `payload` on `pipe` and waits for it to be acknowledged, retransmitting it as
+    /// configured. Timeouts are measured with `clock`
+    ///
+    /// Returns [`crate::Error::NotAcknowledged`] if none of the attempts was acknowledged, and
+    /// [`crate::Error::ValueOutOfBounds`] if the payload doesn't fit.
+    pub fn send(
+        &mut self,
+        pipe: Address,
+        payload: &[u8],
+        clock: &mut impl Clock,
+    ) -> crate::Result<Ack> {
+        let packet = self.packet(pipe, payload, false)?;
+
+        for retransmits in 0..=self.config.retransmits {
+            if retransmits > 0 {
+                let mut delay = time_deadline(self.config.retransmit_delay, clock);
+                while delay().is_ok() {
+                    core::hint::spin_loop();
+                }
+            }
+
+            match self.attempt(pipe, &packet, clock) {
+                Ok(packet) => return Ok(Ack::new(retransmits, packet)),
+                Err(crate::Error::TimedOut | crate::Error::CrcMismatch) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(crate::Error::NotAcknowledged)
+    }
+
+    /// Sends `payload` on `pipe` once, asking the receiver not to acknowledge it
+    pub fn send_no_ack(&mut self, pipe: Address, payload: &[u8]) -> crate::Result<()> {
+        let packet = self.packet(pipe, payload, true)?;
+
+        self.radio.transmit_packet(&packet)
+    }
+
+    /// Sends `packet` on `pipe` once and waits [`Config::ack_timeout`] for the acknowledgement
+    pub(crate) fn attempt(
+        &self,
+        pipe: Address,
+        packet: &packet::Packet,
+        clock: &mut impl Clock,
+    ) -> crate::Result<packet::ReceivedPacket> {
+        // the acknowledgement comes back on the same pipe
+        reg_access::write_rx_address(&self.radio.radio, pipe.into_rx_address());
+
+        self.radio
+            .transmit_then_receive_within(packet, self.config.ack_timeout, clock)
+            .map(|(packet, _)| packet)
+    }
+
+    /// Builds the next packet for `pipe`, and points `TXADDRESS` to it
+    pub(crate) fn packet(
+        &mut self,
+        pipe: Address,
+        payload: &[u8],
+        no_ack: bool,
+    ) -> crate::Result<packet::Packet> {
+        if payload.len() > MAX_PAYLOAD_LENGTH {
+            return Err(crate::Error::ValueOutOfBounds);
+        }
+
+        let pid = &mut self.pids[pipe as usize];
+        let control = PacketControl { pid: *pid, no_ack };
+        *pid = (*pid + 1) & MAX_PID;
+
+        self.radio.set_tx_address(pipe);
+        self.radio
+            .packet_builder()
+            .s1(control.to_s1())
+            .payload(payload)
+            .build()
+    }
+}
+
+/// A packet received by a [`Prx`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Received {
+    packet: packet::ReceivedPacket,
+    control: PacketControl,
+}
+
+impl Received {
+    /// The pipe the packet was received on
+    pub fn pipe(&self) -> Address {
+        self.packet.address()
+    }
+
+    /// The payload of the packet
+    pub fn payload(&self) -> &[u8] {
+        self.packet.packet().payload()
+    }
+
+    /// The packet ID and `NO_ACK` flag of the packet
+    pub fn control(&self) -> PacketControl {
+        self.control
+    }
+
+    /// The packet, with information about its reception
+    pub fn packet(&self) -> &packet::ReceivedPacket {
+        &self.packet
+    }
+}
+
+/// ACK payloads waiting to be sent on one pipe
+#[derive(Copy, Clone)]
+struct AckQueue {
+    payloads: [[u8; MAX_PAYLOAD_LENGTH]; ACK_QUEUE_DEPTH],
+    lens: [u8; ACK_QUEUE_DEPTH],
+    head: usize,
+    len: usize,
+    /// Whether the payload at the head went out with the last acknowledgement
+    sent: bool,
+}
+
+impl AckQueue {
+    const EMPTY: Self = Self {
+        payloads: [[0; MAX_PAYLOAD_LENGTH]; ACK_QUEUE_DEPTH],
+        lens: [0; ACK_QUEUE_DEPTH],
+        head: 0,
+        len: 0,
+        sent: false,
+    };
+
+    fn is_full(&self) -> bool {
+        self.len == ACK_QUEUE_DEPTH
+    }
+
+    fn push(&mut self, payload: &[u8]) -> crate::Result<()> {
+        if payload.len() > MAX_PAYLOAD_LENGTH {
+            return Err(crate::Error::ValueOutOfBounds);
+        }
+        if self.is_full() {
+            return Err(crate::Error::QueueFull);
+        }
+
+        let i = (self.head + self.len) % ACK_QUEUE_DEPTH;
+        self.payloads[i][..payload.len()].copy_from_slice(payload);
+        self.lens[i] = payload.len() as u8;
+        self.len += 1;
+
+        Ok(())
+    }
+
+    /// The transmitter has moved on to a new packet, so it has received the payload which went
+    /// out with the last acknowledgement
+    fn delivered(&mut self) {
+        if self.sent {
+            self.head = (self.head + 1) % ACK_QUEUE_DEPTH;
+            self.len -= 1;
+            self.sent = false;
+        }
+    }
+
+    /// Takes the payload for the next acknowledgement. It stays queued until it's delivered, in
+    /// case the acknowledgement gets lost
+    fn next(&mut self) -> &[u8] {
+        self.sent = self.len > 0;
+        if !self.sent {
+            return &[];
+        }
+
+        &self.payloads[self.head][..usize::from(self.lens[self.head])]
+    }
+
+    fn flush(&mut self) {
+        *self = Self::EMPTY;
+    }
+}
+
+/// A primary receiver. See the [module documentation](self)
+///
+/// Between packets, the radio is disabled: it's ramped up to receive a packet, and once the
+/// packet has been checked, the CPU turns it around to transmit the acknowledgement. Nordic's
+/// implementation arms `DISABLED_TXEN` while receiving instead, so its acknowledgements go out
+/// [`Radio::tifs`] after the packet, while these go out as soon as the CPU gets to them. The
+/// transmitter's [`Config::ack_timeout`] has to leave time for that.
+pub struct Prx<R: RadioRegisters = nrf51_pac::RADIO> {
+    radio: Radio<crate::Disabled, R>,
+    builder: packet::PacketBuilder<'static>,
+    /// The packet ID and CRC of the last packet on each pipe
+    last: [Option<(u8, Option<u32>)>; PIPES],
+    queues: [AckQueue; PIPES],
+}
+
+impl<R: RadioRegisters> Prx<R> {
+    /// Takes over `radio`, which has to be set up for ESB with dynamic payload length, and
+    /// listens on `pipes`
+    ///
+    /// Returns [`crate::Error::ValueOutOfBounds`] if the packet layout of the radio isn't the one
+    /// of ESB.
+    pub fn new(radio: Radio<Enabled<Receiver>, R>, pipes: &[Address]) -> crate::Result<Self> {
+        check_layout(radio.packet_config())?;
+
+        radio.disable_all_rx_addresses().enable_rx_addresses(pipes);
+
+        Ok(Self {
+            builder: radio.packet_builder(),
+            radio: radio.disable(),
+            last: [None; PIPES],
+            queues: [AckQueue::EMPTY; PIPES],
+        })
+    }
+
+    /// Gives back the radio
+    pub fn free(self) -> Radio<Enabled<Receiver>, R> {
+        self.radio.into_receiver()
+    }
+
+    /// Set the frequency packets are received on
+    pub fn set_frequency(&self, freq: Frequency) -> &Self {
+        reg_access::write_frequency(&self.radio.radio, freq.0);
+
+        self
+    }
+
+    /// Receives the next new packet, acknowledging it unless it asks not to be. Waits for
+    /// `cycles` CPU cycles for each packet until returning [`crate::Error::TimedOut`]
+    ///
+    /// Retransmissions of the last packet on a pipe are acknowledged again, with the same ACK
+    /// payload, but not returned. Packets with a [`crate::Error::CrcMismatch`] are dropped
+    /// without being acknowledged, so the transmitter retransmits them.
+    pub fn receive(&mut self, cycles: u32) -> crate::Result<Received> {
+        loop {
+            let packet = match self.radio.receive_packet_with_shortcuts(cycles) {
+                Ok(packet) => packet,
+                Err(crate::Error::CrcMismatch) => continue,
+                Err(e) => return Err(e),
+            };
+            if let Some(received) = self.accept(packet)? {
+                return Ok(received);
+            }
+        }
+    }
+
+    /// Like [`Self::receive`], but times out after `timeout` has passed on `clock`, which
+    /// covers the whole call rather than each packet. See [`crate::clock`] for how accurate that
+    /// is
+    ///
+    /// The acknowledgement of a packet isn't cut short by the timeout, so a packet which arrives
+    /// just before it passes is still returned.
+    pub fn receive_within(
+        &mut self,
+        timeout: Duration,
+        clock: &mut impl Clock,
+    ) -> crate::Result<Received> {
+        let mut deadline = time_deadline(timeout, clock);
+
+        loop {
+            let packet = match self
+                .radio
+                .receive_packet_with_shortcuts_inner(&mut deadline)
+            {
+                Ok(packet) => packet,
+                Err(crate::Error::CrcMismatch) => continue,
+                Err(e) => return Err(e),
+            };
+            if let Some(received) = self.accept(packet)? {
+                return Ok(received);
+            }
+        }
+    }
+
+    /// Acknowledges `packet` unless it asks not to be. Returns `None` if it's a retransmission
+    fn accept(&mut self, packet: packet::ReceivedPacket) -> crate::Result<Option<Received>> {
+        let control = PacketControl::from_s1(packet.packet().s1().unwrap_or(0));
+        let pipe = packet.address() as usize;
+
+        let id = Some((control.pid, packet.crc()));
+        let duplicate = core::mem::replace(&mut self.last[pipe], id) == id;
+        if !duplicate {
+            self.queues[pipe].delivered();
+        }
+
+        if !control.no_ack {
+            let payload = self.queues[pipe].next();
+            let ack = self
+                .builder
+                .clone()
+                .s1(PacketControl {
+                    pid: control.pid,
+                    no_ack: true,
+                }
+                .to_s1())
+                .payload(payload)
+                .build()?;
+
+            // the packet is returned even if the acknowledgement doesn't go out, as the
+            // transmitter retransmits it then, and gets acknowledged for the retransmission
+            self.acknowledge(packet.address(), &ack).ok();
+        }
+
+        if duplicate {
+            return Ok(None);
+        }
+
+        Ok(Some(Received { packet, control }))
+    }
+
+    /// Queues `payload` to be sent along with the next acknowledgement on `pipe`
+    ///
+    /// Returns [`crate::Error::QueueFull`] if [`ACK_QUEUE_DEPTH`] payloads are queued on the pipe
+    /// already, and [`crate::Error::ValueOutOfBounds`] if the payload is longer than
+    /// [`MAX_PAYLOAD_LENGTH`].
+    pub fn queue_ack_payload(&mut self, pipe: Address, payload: &[u8]) -> crate::Result<()> {
+        self.queues[pipe as usize].push(payload)
+    }
+
+    /// Whether no more ACK payloads can be queued on `pipe`
+    pub fn ack_queue_full(&self, pipe: Address) -> bool {
+        self.queues[pipe as usize].is_full()
+    }
+
+    /// Drops the ACK payloads queued on `pipe`, including one which has been sent but may not
+    /// have been received
+    pub fn flush_ack_payloads(&mut self, pipe: Address) {
+        self.queues[pipe as usize].flush();
+    }
+
+    /// Sends the acknowledgement `ack` on `pipe`, giving up after [`ACK_CYCLES`]
+    fn acknowledge(&self, pipe: Address, ack: &packet::Packet) -> crate::Result<()> {
+        reg_access::write_tx_address(&self.radio.radio, pipe.into_tx_address().into());
+        self.radio
+            .transmit_packet_with_shortcuts_inner(ack, cycle_deadline(ACK_CYCLES))
+    }
+}
@@ -0,0 +1,351 @@
+//! Gazell, Nordic's star network on top of Enhanced ShockBurst
+//!
+//! A [`Host`] listens on up to eight pipes and acknowledges what [`Device`]s send it, just like
+//! an [`esb::Prx`] - including ACK payloads, which is how data gets to a device, as devices
+//! always initiate. What Gazell adds is frequency hopping: time is divided into timeslots of
+//! [`Config::timeslot_period`], and the host moves on to the next channel of the
+//! [`ChannelTable`] every [`Config::timeslots_per_channel`] timeslots.
+//!
+//! A device tries once per timeslot. As long as it's in sync, it follows the host's schedule
+//! from the last acknowledgement it received, so it's on the right channel the first time. Once
+//! [`Config::sync_lifetime`] timeslots have passed without an acknowledgement, it's out of sync
+//! and stays on each channel for [`Config::timeslots_per_channel_out_of_sync`] timeslots
+//! instead, which is long enough for the host to come by.
+//!
+//! The host starts the timeslots of its current channel over whenever it receives a packet, so
+//! that the moment a device gets its acknowledgement is a timeslot boundary for both.
+
+use core::time::Duration;
+
+use crate::{
+    Address, Enabled, Frequency, Radio, RadioRegisters, Receiver, Transmitter,
+    clock::Clock,
+    esb::{self, Ack, Prx, Ptx, Received},
+};
+
+/// Maximum number of channels in a [`ChannelTable`]
+pub const MAX_CHANNELS: usize = 16;
+
+/// The channels hosts and devices hop between, in order
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ChannelTable {
+    channels: [Frequency; MAX_CHANNELS],
+    len: usize,
+}
+
+impl ChannelTable {
+    /// Returns [`crate::Error::ValueOutOfBounds`] if there are no channels, or more than
+    /// [`MAX_CHANNELS`]
+    pub fn new(channels: &[Frequency]) -> crate::Result<Self> {
+        if channels.is_empty() || channels.len() > MAX_CHANNELS {
+            return Err(crate::Error::ValueOutOfBounds);
+        }
+
+        let mut table = [Frequency::default(); MAX_CHANNELS];
+        table[..channels.len()].copy_from_slice(channels);
+
+        Ok(Self {
+            channels: table,
+            len: channels.len(),
+        })
+    }
+
+    /// The channels, in the order they're hopped through
+    pub fn channels(&self) -> &[Frequency] {
+        &self.channels[..self.len]
+    }
+
+    /// The channel at `index`, wrapping around at the end of the table
+    fn get(&self, index: usize) -> Frequency {
+        self.channels[index % self.len]
+    }
+}
+
+impl Default for ChannelTable {
+    /// 2404, 2425, 2442, 2463 and 2477 MHz, like Nordic's implementation
+    fn default() -> Self {
+        let mut channels = [Frequency::default(); MAX_CHANNELS];
+        for (channel, mhz) in channels.iter_mut().zip([4, 25, 42, 63, 77]) {
+            *channel = Frequency(mhz);
+        }
+
+        Self { channels, len: 5 }
+    }
+}
+
+/// Timing of a [`Host`] and the [`Device`]s talking to it, which have to agree on all of it
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    /// The channels to hop between
+    pub channels: ChannelTable,
+    /// The length of a timeslot. A device makes one attempt per timeslot, so it has to cover a
+    /// packet and its acknowledgement
+    #[cfg_attr(feature = "defmt", defmt(Debug2Format))]
+    pub timeslot_period: Duration,
+    /// How many timeslots the host, and devices in sync, stay on a channel
+    pub timeslots_per_channel: u8,
+    /// How many timeslots devices which are out of sync stay on a channel. To be sure to meet
+    /// the host, this has to be at least the number of channels times
+    /// [`Self::timeslots_per_channel`]
+    pub timeslots_per_channel_out_of_sync: u8,
+    /// How many timeslots after its last acknowledgement a device is still in sync
+    pub sync_lifetime: u32,
+    /// How many attempts a device makes at sending a packet. At least one is always made
+    pub max_tx_attempts: u16,
+}
+
+impl Config {
+    /// The timeslot period in microseconds, at least one
+    fn period(&self) -> u64 {
+        u64::try_from(self.timeslot_period.as_micros())
+            .unwrap_or(u64::MAX)
+            .max(1)
+    }
+}
+
+impl Default for Config {
+    /// Nordic's defaults: the default [`ChannelTable`], 600 µs timeslots, 2 timeslots per
+    /// channel, 15 when out of sync and a sync lifetime of three rounds through the table.
+    /// Unlike Nordic's, devices give up after 100 attempts
+    fn default() -> Self {
+        Self {
+            channels: ChannelTable::default(),
+            timeslot_period: Duration::from_micros(600),
+            timeslots_per_channel: 2,
+            timeslots_per_channel_out_of_sync: 15,
+            sync_lifetime: 3 * 5 * 2,
+            max_tx_attempts: 100,
+        }
+    }
+}
+
+/// A timeslot which started on a channel of the table, from which the following ones are
+/// counted
+#[derive(Copy, Clone, Debug)]
+struct Anchor {
+    at: u64,
+    index: usize,
+}
+
+/// Where a schedule is at a point in time
+struct Slot {
+    /// The index of the channel in the table
+    index: usize,
+    /// When the timeslot ends
+    end: u64,
+    /// When the schedule moves on to the next channel
+    hop: u64,
+}
+
+impl Anchor {
+    /// Where the schedule starting at this anchor, staying `per_channel` timeslots on each
+    /// channel, is at `now`
+    fn slot(&self, now: u64, config: &Config, per_channel: u8) -> Slot {
+        let period = config.period();
+        let per_channel = u64::from(per_channel.max(1));
+
+        let slot = now.saturating_sub(self.at) / period;
+        let hops = slot / per_channel;
+
+        Slot {
+            index: (self.index + hops as usize) % config.channels.len,
+            end: self.at + (slot + 1) * period,
+            hop: self.at + (hops + 1) * per_channel * period,
+        }
+    }
+}
+
+/// The receiving end of a Gazell network. See the [module documentation](self)
+pub struct Host<R: RadioRegisters = nrf51_pac::RADIO> {
+    prx: Prx<R>,
+    config: Config,
+    anchor: Option<Anchor>,
+}
+
+impl<R: RadioRegisters> Host<R> {
+    /// Takes over `radio`, which has to be set up for ESB with dynamic payload length, listening
+    /// on `pipes`
+    ///
+    /// Returns [`crate::Error::ValueOutOfBounds`] if the packet layout of the radio isn't the one
+    /// of ESB.
+    pub fn new(
+        radio: Radio<Enabled<Receiver>, R>,
+        pipes: &[Address],
+        config: Config,
+    ) -> crate::Result<Self> {
+        Ok(Self {
+            prx: Prx::new(radio, pipes)?,
+            config,
+            anchor: None,
+        })
+    }
+
+    /// Gives back the radio
+    pub fn free(self) -> Radio<Enabled<Receiver>, R> {
+        self.prx.free()
+    }
+
+    /// Receives the next new packet from any device, hopping channels while waiting. Times out
+    /// after `timeout` has passed on `clock`
+    ///
+    /// The schedule starts with the first call and keeps running in between calls. A packet
+    /// which arrives just before a hop is still acknowledged and returned.
+    pub fn receive(
+        &mut self,
+        timeout: Duration,
+        clock: &mut impl Clock,
+    ) -> crate::Result<Received> {
+        let start = clock.now_micros();
+        let until = start.saturating_add(u64::try_from(timeout.as_micros()).unwrap_or(u64::MAX));
+        let anchor = *self.anchor.get_or_insert(Anchor {
+            at: start,
+            index: 0,
+        });
+
+        loop {
+            let now = clock.now_micros();
+            if now >= until {
+                return Err(crate::Error::TimedOut);
+            }
+
+            let slot = anchor.slot(now, &self.config, self.config.timeslots_per_channel);
+            self.prx.set_frequency(self.config.channels.get(slot.index));
+
+            // only listening stops at the hop, an acknowledgement may go out after it
+            let listen = Duration::from_micros(slot.hop.min(until) - now);
+            match self.prx.receive_within(listen, clock) {
+                Ok(received) => {
+                    self.anchor = Some(Anchor {
+                        at: clock.now_micros(),
+                        index: slot.index,
+                    });
+
+                    return Ok(received);
+                }
+                Err(crate::Error::TimedOut) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Queues `payload` to be sent to the device on `pipe` with the next acknowledgement. See
+    /// [`Prx::queue_ack_payload`]
+    pub fn queue_ack_payload(&mut self, pipe: Address, payload: &[u8]) -> crate::Result<()> {
+        self.prx.queue_ack_payload(pipe, payload)
+    }
+
+    /// Whether no more ACK payloads can be queued on `pipe`
+    pub fn ack_queue_full(&self, pipe: Address) -> bool {
+        self.prx.ack_queue_full(pipe)
+    }
+
+    /// Drops the ACK payloads queued on `pipe`
+    pub fn flush_ack_payloads(&mut self, pipe: Address) {
+        self.prx.flush_ack_payloads(pipe);
+    }
+}
+
+/// The sending end of a Gazell network. See the [module documentation](self)
+pub struct Device<R: RadioRegisters = nrf51_pac::RADIO> {
+    ptx: Ptx<R>,
+    config: Config,
+    /// The host's schedule, as of the last acknowledgement
+    sync: Option<Anchor>,
+    /// The index of the channel the last attempt was made on
+    index: usize,
+}
+
+impl<R: RadioRegisters> Device<R> {
+    /// Takes over `radio`, which has to be set up for ESB with dynamic payload length
+    ///
+    /// Returns [`crate::Error::ValueOutOfBounds`] if the packet layout of the radio isn't the one
+    /// of ESB.
+    pub fn new(radio: Radio<Enabled<Transmitter>, R>, config: Config) -> crate::Result<Self> {
+        let esb = esb::Config {
+            retransmits: 0,
+            retransmit_delay: Duration::ZERO,
+            ack_timeout: config.timeslot_period,
+        };
+
+        Ok(Self {
+            ptx: Ptx::new(radio, esb)?,
+            config,
+            sync: None,
+            index: 0,
+        })
+    }
+
+    /// Gives back the radio
+    pub fn free(self) -> Radio<Enabled<Transmitter>, R> {
+        self.ptx.free()
+    }
+
+    /// Whether the device still knows which channel the host is on
+    pub fn is_in_sync(&self, clock: &mut impl Clock) -> bool {
+        self.synced(clock.now_micros()).is_some()
+    }
+
+    /// Sends `payload` to the host on `pipe`, making one attempt per timeslot until it's
+    /// acknowledged. Timeslots are measured with `clock`
+    ///
+    /// Returns [`crate::Error::NotAcknowledged`] if none of [`Config::max_tx_attempts`] attempts
+    /// was acknowledged, and [`crate::Error::ValueOutOfBounds`] if the payload doesn't fit.
+    pub fn send(
+        &mut self,
+        pipe: Address,
+        payload: &[u8],
+        clock: &mut impl Clock,
+    ) -> crate::Result<Ack> {
+        let packet = self.ptx.packet(pipe, payload, false)?;
+        let mut out_of_sync = None;
+
+        for attempt in 0..self.config.max_tx_attempts.max(1) {
+            let now = clock.now_micros();
+            let slot = match self.synced(now) {
+                Some(anchor) => anchor.slot(now, &self.config, self.config.timeslots_per_channel),
+                None => out_of_sync
+                    .get_or_insert(Anchor {
+                        at: now,
+                        index: self.index,
+                    })
+                    .slot(
+                        now,
+                        &self.config,
+                        self.config.timeslots_per_channel_out_of_sync,
+                    ),
+            };
+
+            self.index = slot.index;
+            self.ptx.set_frequency(self.config.channels.get(slot.index));
+
+            match self.ptx.attempt(pipe, &packet, clock) {
+                Ok(ack) => {
+                    self.sync = Some(Anchor {
+                        at: clock.now_micros(),
+                        index: slot.index,
+                    });
+
+                    return Ok(Ack::new(u8::try_from(attempt).unwrap_or(u8::MAX), ack));
+                }
+                Err(crate::Error::TimedOut | crate::Error::CrcMismatch) => {}
+                Err(e) => return Err(e),
+            }
+
+            // the next attempt goes out in the next timeslot
+            while clock.now_micros() < slot.end {
+                core::hint::spin_loop();
+            }
+        }
+
+        Err(crate::Error::NotAcknowledged)
+    }
+
+    /// The host's schedule, if the device is still in sync at `now`
+    fn synced(&self, now: u64) -> Option<Anchor> {
+        let lifetime = u64::from(self.config.sync_lifetime) * self.config.period();
+
+        self.sync.filter(|a| now.saturating_sub(a.at) < lifetime)
+    }
+}
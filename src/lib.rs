@@ -1,5 +1,5 @@
 #![no_std]
-#![no_main]
+#![cfg_attr(not(test), no_main)]
 #![deny(missing_docs)]
 #![deny(clippy::unwrap_used)]
 
@@ -17,10 +17,29 @@
 //! # Performance
 //!
 //! Speed isn't the main focus of this interface - interrupts generally aren't used; everything is
-//! awaited in a spinlock.
-
+//! awaited in a spinlock. The exception is the `async` feature, which adds `async` versions of
+//! the transmit and receive functions that are woken up by the RADIO interrupt.
+
+#[cfg(feature = "async")]
+mod asynch;
+pub mod ble;
+pub mod clock;
+#[cfg(feature = "embassy")]
+pub mod embassy;
+pub mod esb;
+pub mod gazell;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod nrf24;
 pub mod packet;
+pub mod preset;
 mod reg_access;
+pub mod registers;
+#[cfg(feature = "sim")]
+pub mod sim;
+pub mod turnaround;
+
+pub use registers::RadioRegisters;
 
 use core::marker::PhantomData;
 
@@ -39,14 +58,37 @@ pub enum Error {
     /// A value that you tried to convert to another falls out of range of the given container.
     #[error("the value is out of bounds of the requested container")]
     ValueOutOfBounds,
+
+    /// A packet was received, but its CRC didn't match.
+    #[error("the CRC of the received packet didn't match")]
+    CrcMismatch,
+
+    /// The operation was cancelled before it could complete.
+    #[error("the operation was cancelled")]
+    Cancelled,
+
+    /// A packet was being received, but it was rejected after inspecting its header.
+    #[error("the packet was rejected")]
+    Rejected,
+
+    /// A packet was sent, but the receiver didn't acknowledge it.
+    #[error("the packet wasn't acknowledged")]
+    NotAcknowledged,
+
+    /// A queue had no room for another element.
+    #[error("the queue is full")]
+    QueueFull,
 }
 
 /// Result type returned by functions
 pub type Result<T> = core::result::Result<T, Error>;
 
 /// The main RADIO abstraction
-pub struct Radio<T> {
-    radio: nrf51_pac::RADIO,
+///
+/// `R` is what the registers are accessed through - the PAC peripheral, unless you're running the
+/// driver against something else (see [`RadioRegisters`]).
+pub struct Radio<T, R = nrf51_pac::RADIO> {
+    radio: R,
     _marker: PhantomData<T>,
 }
 
@@ -63,14 +105,14 @@ pub struct Disabled;
 /// Converts the radio from one state to another
 macro_rules! convert_radio {
     ($radio:expr, $into_state:ident) => {
-        crate::Radio::<$into_state> {
+        crate::Radio::<$into_state, _> {
             radio: $radio,
             _marker: PhantomData,
         }
     };
 }
 
-impl crate::Radio<()> {
+impl<R: RadioRegisters> crate::Radio<(), R> {
     /// Constructs a new [`crate::Radio`], setting the radio state to disabled
     ///
     /// If are constructing [`crate::Radio`] for the first time in your program, **you most
@@ -80,7 +122,7 @@ impl crate::Radio<()> {
     ///
     /// You will probably want to use this function if you've already constructed [`crate::Radio`]
     /// before, but need to construct a new struct for some reason.
-    pub fn new(radio: nrf51_pac::RADIO) -> crate::Radio<Disabled> {
+    pub fn new(radio: R) -> crate::Radio<Disabled, R> {
         reg_access::disable(&radio);
 
         let radio = convert_radio!(radio, Disabled);
@@ -91,7 +133,7 @@ impl crate::Radio<()> {
 
     /// Like [`Self::new`], but sets many registers to zero, so that packets don't exhibit possibly
     /// unexpected behaviour
-    pub fn new_zeroed(radio: nrf51_pac::RADIO) -> crate::Radio<Disabled> {
+    pub fn new_zeroed(radio: R) -> crate::Radio<Disabled, R> {
         let radio = Self::new(radio);
 
         let r = &radio.radio;
@@ -107,7 +149,7 @@ impl crate::Radio<()> {
 macro_rules! impl_into_rx {
     () => {
         /// Switch the radio into receiver mode
-        pub fn into_receiver(self) -> $crate::Radio<Enabled<Receiver>> {
+        pub fn into_receiver(self) -> $crate::Radio<Enabled<Receiver>, R> {
             reg_access::disable(&self.radio);
             self.wait_for_state(State::DISABLED);
 
@@ -126,7 +168,7 @@ macro_rules! impl_into_rx {
 macro_rules! impl_into_tx {
     () => {
         /// Switch the radio into transmitter mode
-        pub fn into_transmitter(self) -> crate::Radio<Enabled<Transmitter>> {
+        pub fn into_transmitter(self) -> crate::Radio<Enabled<Transmitter>, R> {
             reg_access::disable(&self.radio);
             self.wait_for_state(State::DISABLED);
 
@@ -145,7 +187,7 @@ macro_rules! impl_into_tx {
 macro_rules! impl_disable {
     () => {
         /// Disable the radio
-        pub fn disable(self) -> crate::Radio<Disabled> {
+        pub fn disable(self) -> crate::Radio<Disabled, R> {
             reg_access::disable(&self.radio);
             self.wait_for_state(State::DISABLED);
 
@@ -154,7 +196,7 @@ macro_rules! impl_disable {
     };
 }
 
-impl crate::Radio<Disabled> {
+impl<R: RadioRegisters> crate::Radio<Disabled, R> {
     impl_into_rx!();
     impl_into_tx!();
 }
@@ -163,7 +205,7 @@ impl crate::Radio<Disabled> {
 pub const FREQUENCY_OFFSET: u32 = 2400;
 
 /// The frequency, specified as `2400 MHz + f [MHz]`
-#[derive(Copy, Clone, Eq, PartialEq, PartialOrd, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, PartialOrd, Debug, Default)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Frequency(u32);
 
@@ -197,28 +239,147 @@ pub use nrf51_pac::radio::pcnf1::ENDIAN_A as Endianness;
 
 /// Interrupts that can be invoked by the radio
 #[repr(u32)]
-#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, strum::FromRepr)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Interrupt {
     /// RADIO has ramped up and is ready to be started
     Ready = 1 << 0,
     /// Address sent or received
-    Address = 1 << 2,
+    Address = 1 << 1,
     /// Packet payload sent or received
-    Payload = 1 << 3,
+    Payload = 1 << 2,
     /// Packet sent or received
-    End = 1 << 4,
+    End = 1 << 3,
     /// RADIO has been disabled
-    Disabled = 1 << 5,
+    Disabled = 1 << 4,
     /// A device address match occurred on the last received packet
-    DevMatch = 1 << 6,
+    DevMatch = 1 << 5,
     /// No device address match occurred on the last received packet
-    DevMiss = 1 << 7,
+    DevMiss = 1 << 6,
     /// Sampling of receive signal strength complete. A new RSSI sample is ready for readout from
     /// the RSSISAMPLE register
-    RSSIEnd = 1 << 8,
+    RSSIEnd = 1 << 7,
+    /// Bit counter reached bit count value specified in the BCC register
+    BCMatch = 1 << 10,
+}
+
+impl Interrupt {
+    /// Get the event which triggers this interrupt
+    pub fn event(self) -> Event {
+        match self {
+            Interrupt::Ready => Event::Ready,
+            Interrupt::Address => Event::Address,
+            Interrupt::Payload => Event::Payload,
+            Interrupt::End => Event::End,
+            Interrupt::Disabled => Event::Disabled,
+            Interrupt::DevMatch => Event::DevMatch,
+            Interrupt::DevMiss => Event::DevMiss,
+            Interrupt::RSSIEnd => Event::RSSIEnd,
+            Interrupt::BCMatch => Event::BCMatch,
+        }
+    }
+}
+
+/// Events raised by the radio. An event stays raised until it's cleared
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Event {
+    /// RADIO has ramped up and is ready to be started
+    Ready,
+    /// Address sent or received
+    Address,
+    /// Packet payload sent or received
+    Payload,
+    /// Packet sent or received
+    End,
+    /// RADIO has been disabled
+    Disabled,
+    /// A device address match occurred on the last received packet
+    DevMatch,
+    /// No device address match occurred on the last received packet
+    DevMiss,
+    /// Sampling of receive signal strength complete
+    RSSIEnd,
     /// Bit counter reached bit count value specified in the BCC register
-    BCMatch = 1 << 9,
+    BCMatch,
+}
+
+/// A set of [`Interrupt`]s, which can be iterated over
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Interrupts(BitMask<u32>);
+
+impl Interrupts {
+    /// Checks whether `interrupt` is in the set
+    pub fn contains(&self, interrupt: Interrupt) -> bool {
+        self.0 & interrupt as u32 != 0
+    }
+
+    /// Checks whether the set is empty
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Get the set as a mask, laid out like the `INTENSET` register
+    pub fn bits(&self) -> BitMask<u32> {
+        self.0
+    }
+}
+
+impl Iterator for Interrupts {
+    type Item = Interrupt;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.0 != 0 {
+            let bit = 1 << count_mask_shift(self.0);
+            self.0 &= !bit;
+
+            if let Some(interrupt) = Interrupt::from_repr(bit) {
+                return Some(interrupt);
+            }
+        }
+
+        None
+    }
+}
+
+/// Handles a RADIO interrupt. Call this from the `RADIO` interrupt handler
+///
+/// Clears the events of all the enabled interrupts that fired (otherwise the interrupt would
+/// keep firing), and reports which ones they were.
+///
+/// Keep in mind that the blocking functions of [`Radio`] wait for events by polling them, so they
+/// will time out if an interrupt is enabled for an event they're waiting on.
+///
+/// With the `async` feature, this also wakes up the future waiting for the radio.
+pub fn on_interrupt() -> Interrupts {
+    // SAFETY: only the event registers of the enabled interrupts are touched. Clearing an event
+    // doesn't interfere with anything the owner of the radio may be doing, other than what is
+    // documented above
+    let radio = unsafe { nrf51_pac::Peripherals::steal() }.RADIO;
+
+    on_interrupt_with(&radio)
+}
+
+/// Like [`on_interrupt`], but handles the interrupt of the radio behind `radio`
+///
+/// This is for registers other than the nRF51's own, such as a mock or simulated radio, which
+/// has no interrupt line - call it wherever the interrupt would fire.
+pub fn on_interrupt_with<R: RadioRegisters>(radio: &R) -> Interrupts {
+    let enabled = Interrupts(reg_access::read_interrupts(radio));
+    let mut fired = 0;
+
+    for interrupt in enabled {
+        if reg_access::events::occurred(radio, interrupt.event()) {
+            reg_access::events::clear(radio, interrupt.event());
+            fired |= interrupt as u32;
+        }
+    }
+
+    #[cfg(feature = "async")]
+    asynch::on_interrupt(Interrupts(fired));
+
+    Interrupts(fired)
 }
 
 /// A value that can be XOR'ed in a certain way in order to get more information
@@ -226,7 +387,7 @@ pub type BitMask<T> = T;
 
 // TODO: some of these functions should maybe be moved to the `crate::Radio<T>` impl, as they aren't
 // specific to the enabled state
-impl<T> crate::Radio<Enabled<T>> {
+impl<T, R: RadioRegisters> crate::Radio<Enabled<T>, R> {
     impl_disable!();
 
     /// Set the frequency on which the radio operates
@@ -300,28 +461,312 @@ impl<T> crate::Radio<Enabled<T>> {
         reg_access::write_s1_len(&self.radio, len);
     }
 
+    /// Set the length of the base part of the on-air addresses
+    pub fn set_base_address_length(&self, len: BaseAddressLength) -> &Self {
+        reg_access::write_balen(&self.radio, len.0);
+
+        self
+    }
+
+    /// Get the length of the base part of the on-air addresses. Returns `None` if the register
+    /// value is invalid, which is the case after a reset
+    pub fn base_address_length(&self) -> Option<BaseAddressLength> {
+        BaseAddressLength::from_bytes(reg_access::read_balen(&self.radio))
+    }
+
+    /// Set the on-air address of a logical address
+    ///
+    /// The radio only has two base address registers: one for [`Address::A`], and one shared by
+    /// all the other addresses. **Setting the physical address of any of [`Address::B`] to
+    /// [`Address::H`] changes the base address of all of them**, only the prefix is their own.
+    ///
+    /// Returns [`crate::Error::ValueOutOfBounds`] if the base address doesn't fit into the
+    /// [`BaseAddressLength`] the radio is set to, or if no valid base address length is set.
+    pub fn set_physical_address(
+        &self,
+        address: Address,
+        physical: PhysicalAddress,
+    ) -> crate::Result<&Self> {
+        let balen = self
+            .base_address_length()
+            .ok_or(crate::Error::ValueOutOfBounds)?;
+
+        let base = physical
+            .base
+            .checked_shl(balen.register_shift())
+            .filter(|b| b >> balen.register_shift() == physical.base)
+            .ok_or(crate::Error::ValueOutOfBounds)?;
+
+        reg_access::write_base(&self.radio, address.base_index(), base);
+        reg_access::write_prefix(&self.radio, address as u8, physical.prefix);
+
+        Ok(self)
+    }
+
+    /// Get the on-air address of a logical address. Returns `None` if no valid base address
+    /// length is set
+    pub fn physical_address(&self, address: Address) -> Option<PhysicalAddress> {
+        let balen = self.base_address_length()?;
+
+        let base = reg_access::read_base(&self.radio, address.base_index());
+        let prefix = reg_access::read_prefix(&self.radio, address as u8);
+
+        Some(PhysicalAddress {
+            base: base >> balen.register_shift(),
+            prefix,
+        })
+    }
+
+    /// Set the CRC configuration
+    ///
+    /// Returns [`crate::Error::ValueOutOfBounds`] if the polynomial or initial value is bigger
+    /// than [`MAX_CRC_VALUE`]
+    pub fn set_crc_config(&self, config: CrcConfig) -> crate::Result<&Self> {
+        if config.polynomial > MAX_CRC_VALUE || config.init > MAX_CRC_VALUE {
+            return Err(crate::Error::ValueOutOfBounds);
+        }
+
+        reg_access::write_crccnf(&self.radio, config.len, config.skip_address);
+        reg_access::write_crc_poly(&self.radio, config.polynomial);
+        reg_access::write_crc_init(&self.radio, config.init);
+
+        Ok(self)
+    }
+
+    /// Get the CRC configuration the radio is set to
+    pub fn crc_config(&self) -> CrcConfig {
+        CrcConfig {
+            len: reg_access::read_crc_len(&self.radio),
+            skip_address: reg_access::read_crc_skip_address(&self.radio),
+            polynomial: reg_access::read_crc_poly(&self.radio),
+            init: reg_access::read_crc_init(&self.radio),
+        }
+    }
+
+    /// Enable or disable data whitening
+    pub fn set_whitening(&self, enabled: bool) -> &Self {
+        reg_access::write_whitening(&self.radio, enabled);
+
+        self
+    }
+
+    /// Get whether data whitening is enabled
+    pub fn whitening(&self) -> bool {
+        reg_access::read_whitening(&self.radio)
+    }
+
+    /// Set the initial value of the data whitening LFSR. Bit 6 is hard-wired to one in the
+    /// hardware, so writing zero to it has no effect
+    ///
+    /// Returns [`crate::Error::ValueOutOfBounds`] if `iv` is bigger than
+    /// [`packet::MAX_WHITENING_IV`]. See [`packet::Whitener`] for a software implementation
+    pub fn set_whitening_iv(&self, iv: u8) -> crate::Result<&Self> {
+        if iv > packet::MAX_WHITENING_IV {
+            return Err(crate::Error::ValueOutOfBounds);
+        }
+
+        reg_access::write_whitening_iv(&self.radio, iv);
+
+        Ok(self)
+    }
+
+    /// Get the initial value of the data whitening LFSR
+    pub fn whitening_iv(&self) -> u8 {
+        reg_access::read_whitening_iv(&self.radio)
+    }
+
+    /// Set the packet configuration, writing each of the `PCNF0` and `PCNF1` registers once
+    ///
+    /// Returns [`crate::Error::ValueOutOfBounds`] if the configuration is invalid, see
+    /// [`packet::PacketConfig::validate`]
+    pub fn set_packet_config(&self, config: packet::PacketConfig) -> crate::Result<&Self> {
+        config.validate()?;
+
+        reg_access::write_packet_config(&self.radio, &config);
+
+        Ok(self)
+    }
+
+    /// Get the packet configuration the radio is set to. Returns `None` if the base address
+    /// length in the register is invalid, which is the case after a reset
+    pub fn packet_config(&self) -> Option<packet::PacketConfig> {
+        Some(packet::PacketConfig {
+            lf_len: self.lf_len(),
+            s0_len: self.s0_len(),
+            s1_len: self.s1_len(),
+            maxlen: reg_access::read_maxlen(&self.radio),
+            statlen: reg_access::read_statlen(&self.radio),
+            balen: self.base_address_length()?,
+            endian: self.endianness(),
+            whiten: self.whitening(),
+        })
+    }
+
+    /// Returns a [`packet::PacketBuilder`] with the field lengths, endianness and static length
+    /// the radio is currently set to
+    pub fn packet_builder(&self) -> packet::PacketBuilder<'static> {
+        packet::Packet::builder()
+            .lf_len(self.lf_len())
+            .s0_len(self.s0_len())
+            .s1_len(self.s1_len())
+            .endianness(self.endianness())
+            .statlen(reg_access::read_statlen(&self.radio))
+    }
+
     /// Returns a mask on which you can try bit ANDing to check the raised interrupts
     pub fn read_interrupts(&self) -> BitMask<u32> {
         reg_access::read_interrupts(&self.radio)
     }
 
-    /// Set the pointer to a packet which should be sent, or set the pointer to a packet buffer to
-    /// which a received packet should be written
-    ///
-    /// # Safety
-    ///
-    /// The pointee MUST be a buffer or otherwise writable memory location.
+    /// Enable an interrupt. The `RADIO` interrupt also has to be unmasked in the NVIC, and its
+    /// handler should call [`crate::on_interrupt`]
+    pub fn enable_interrupt(&self, interrupt: Interrupt) -> &Self {
+        reg_access::enable_interrupt(&self.radio, interrupt);
+
+        self
+    }
+
+    /// Disable an interrupt
+    pub fn disable_interrupt(&self, interrupt: Interrupt) -> &Self {
+        reg_access::disable_interrupt(&self.radio, interrupt);
+
+        self
+    }
+
+    /// Get the interrupts that are enabled
+    pub fn enabled_interrupts(&self) -> Interrupts {
+        Interrupts(reg_access::read_interrupts(&self.radio))
+    }
+
+    /// Set the number of bits after the address at which the bit counter raises
+    /// [`Event::BCMatch`] (`BCC`)
     ///
-    /// The pointer MUST be aligned and MUST NOT be dangling, otherwise **UB will be invoked.**
-    unsafe fn set_packet_ptr<P>(&self, ptr: *mut P) -> &Self {
-        reg_access::set_packet_ptr(&self.radio, ptr);
+    /// The value can be changed while the counter is running, to get notified again later on in
+    /// the same packet.
+    pub fn set_bit_counter_compare(&self, bits: u32) -> &Self {
+        reg_access::write_bcc(&self.radio, bits);
 
         self
     }
+
+    /// Get the number of bits at which the bit counter raises [`Event::BCMatch`]
+    pub fn bit_counter_compare(&self) -> u32 {
+        reg_access::read_bcc(&self.radio)
+    }
+
+    /// Start the bit counter. It's usually started by the [`Shortcuts::ADDRESS_BCSTART`]
+    /// shortcut instead, so that it starts counting right after the address
+    pub fn start_bit_counter(&self) -> &Self {
+        reg_access::tasks::bcstart(&self.radio);
+
+        self
+    }
+
+    /// Stop the bit counter
+    pub fn stop_bit_counter(&self) -> &Self {
+        reg_access::tasks::bcstop(&self.radio);
+
+        self
+    }
+
+    /// Wait until the radio raises the `END` event, calling `poll` on every iteration before
+    /// checking for it
+    ///
+    /// If `poll` returns an error, the packet in flight is stopped and the error is returned -
+    /// unless the packet finished in the meantime, in which case it counts as a success.
+    fn wait_for_end(
+        &self,
+        idle: State,
+        mut poll: impl FnMut() -> crate::Result<()>,
+    ) -> crate::Result<()> {
+        loop {
+            if let Err(e) = poll() {
+                reg_access::tasks::stop(&self.radio);
+                self.wait_for_state(idle);
+
+                if self.event_occurred(Event::End) {
+                    return Ok(());
+                }
+
+                return Err(e);
+            }
+
+            if self.event_occurred(Event::End) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Returns a function for [`Radio::wait_for_end`] which fails with [`crate::Error::TimedOut`]
+/// after being called `cycles` times
+fn cycle_deadline(mut cycles: u32) -> impl FnMut() -> crate::Result<()> {
+    move || {
+        if cycles == 0 {
+            return Err(crate::Error::TimedOut);
+        }
+
+        cycles -= 1;
+        Ok(())
+    }
+}
+
+/// Returns a function for [`Radio::wait_for_end`] which fails with [`crate::Error::TimedOut`]
+/// once `timeout` has passed on `clock`
+fn time_deadline(
+    timeout: core::time::Duration,
+    clock: &mut impl clock::Clock,
+) -> impl FnMut() -> crate::Result<()> {
+    let timeout = u64::try_from(timeout.as_micros()).unwrap_or(u64::MAX);
+    let start = clock.now_micros();
+
+    move || {
+        if clock.now_micros().wrapping_sub(start) >= timeout {
+            return Err(crate::Error::TimedOut);
+        }
+
+        Ok(())
+    }
+}
+
+/// The type of the inspection function for [`Radio::receive_packet_inner`] when there's none
+type NoInspection = (u32, fn(&packet::Packet) -> bool);
+
+/// Returns a function for [`Radio::wait_for_end`] which fails with [`crate::Error::Cancelled`]
+/// once `cancel` returns `true`
+fn cancellation(mut cancel: impl FnMut() -> bool) -> impl FnMut() -> crate::Result<()> {
+    move || {
+        if cancel() {
+            return Err(crate::Error::Cancelled);
+        }
+
+        Ok(())
+    }
 }
 
 pub use nrf51_pac::radio::txpower::TXPOWER_A as TxPower;
 
+pub use nrf51_pac::radio::crccnf::LEN_A as CrcLength;
+
+/// Maximum value of the CRC polynomial and initial value, as they are at most 24 bits long
+pub const MAX_CRC_VALUE: u32 = 0xFF_FFFF;
+
+/// CRC configuration of the radio
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CrcConfig {
+    /// Length of the CRC in bytes, or [`CrcLength::DISABLED`]
+    #[cfg_attr(feature = "defmt", defmt(Debug2Format))]
+    pub len: CrcLength,
+    /// Whether the address is left out of the CRC calculation
+    pub skip_address: bool,
+    /// The CRC polynomial. Each term is represented by one bit, except for the `x^0` term, which
+    /// is always present and has no bit. For example, `x^16 + x^12 + x^5 + 1` is `0x1021`
+    pub polynomial: u32,
+    /// The initial value of the CRC calculation
+    pub init: u32,
+}
+
 /// Logical address. Can be used for reception or transmission
 ///
 /// # Safety
@@ -367,9 +812,82 @@ impl Address {
     fn from_tx_address(addr: u8) -> Option<Self> {
         Self::from_repr(addr)
     }
+
+    /// Index of the `BASEn` register holding the base address of this logical address
+    fn base_index(self) -> u8 {
+        match self {
+            Address::A => 0,
+            _ => 1,
+        }
+    }
+}
+
+/// Minimum length of the base address
+pub const MIN_BASE_ADDRESS_LENGTH_BYTES: u8 = 2;
+/// Maximum length of the base address
+pub const MAX_BASE_ADDRESS_LENGTH_BYTES: u8 = 4;
+
+/// Length of the base part of a [`PhysicalAddress`]. See [MIN_BASE_ADDRESS_LENGTH_BYTES] and
+/// [MAX_BASE_ADDRESS_LENGTH_BYTES]
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BaseAddressLength(pub(crate) u8);
+
+impl BaseAddressLength {
+    /// Convert to this value from number of bytes
+    pub fn from_bytes(len: u8) -> Option<Self> {
+        match len {
+            MIN_BASE_ADDRESS_LENGTH_BYTES..=MAX_BASE_ADDRESS_LENGTH_BYTES => Some(Self(len)),
+            _ => None,
+        }
+    }
+
+    /// Get the length in bytes
+    pub fn bytes(&self) -> u8 {
+        self.0
+    }
+
+    /// How far the base address has to be shifted to end up in the upper bytes of a `BASEn`
+    /// register, which is where the radio takes it from
+    fn register_shift(&self) -> u32 {
+        8 * u32::from(MAX_BASE_ADDRESS_LENGTH_BYTES - self.0)
+    }
 }
 
-impl crate::Radio<Enabled<Transmitter>> {
+/// Number of device addresses the device address match unit can hold
+pub const MAX_DEVICE_ADDRESSES: u8 = 8;
+
+/// A device address for the device address match unit
+///
+/// The unit compares the first 48 bits of the payload of a received packet against the
+/// enabled device addresses, and raises [`Event::DevMatch`] or [`Event::DevMiss`]. This is how
+/// BLE advertising addresses are filtered: the payload starts with the advertiser's address,
+/// and bit 6 of the `S0` field is its `TxAdd` bit.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DeviceAddress {
+    /// The lower 32 bits of the address (`DAB`)
+    pub base: u32,
+    /// The upper 16 bits of the address (`DAP`)
+    pub prefix: u16,
+    /// The value the `TxAdd` bit of the packet has to have for the address to match
+    pub tx_add: bool,
+}
+
+/// The address that is actually sent over the air for a logical [`Address`]
+///
+/// The on-air address consists of the prefix byte followed by the lowest
+/// [`BaseAddressLength`] bytes of the base address.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PhysicalAddress {
+    /// The base address
+    pub base: u32,
+    /// The address prefix
+    pub prefix: u8,
+}
+
+impl<R: RadioRegisters> crate::Radio<Enabled<Transmitter>, R> {
     impl_into_rx!();
 
     /// Set the transmission power
@@ -387,7 +905,7 @@ impl crate::Radio<Enabled<Transmitter>> {
 
     /// Sets the logical address to transmit from
     pub fn set_tx_address(&self, address: Address) -> &Self {
-        reg_access::write_tx_address(&self.radio, address as u32);
+        reg_access::write_tx_address(&self.radio, address.into_tx_address().into());
 
         self
     }
@@ -405,16 +923,75 @@ impl crate::Radio<Enabled<Transmitter>> {
 
         Address::from_tx_address(a).expect("invalid tx address; if you're seeing this it's a bug")
     }
+
+    /// Transmits a packet, waiting for `cycles` CPU cycles until returning
+    /// [`crate::Error::TimedOut`]
+    ///
+    /// The transmission is complete once the radio raises [`Event::End`]. If it times out, it is
+    /// stopped before this function returns, so the radio is back in [`State::TX_IDLE`].
+    ///
+    /// Returns [`crate::Error::ValueOutOfBounds`] if the payload is longer than the maximum
+    /// length the radio is set to, as it would get truncated.
+    pub fn transmit_packet_with_timeout(
+        &self,
+        packet: &packet::Packet,
+        cycles: u32,
+    ) -> crate::Result<()> {
+        self.transmit_packet_inner(packet, cycle_deadline(cycles))
+    }
+
+    /// Like [`Self::transmit_packet_with_timeout`], but times out after `timeout` has passed on
+    /// `clock`. See [`clock`] for how accurate that is
+    pub fn transmit_packet_within(
+        &self,
+        packet: &packet::Packet,
+        timeout: core::time::Duration,
+        clock: &mut impl clock::Clock,
+    ) -> crate::Result<()> {
+        self.transmit_packet_inner(packet, time_deadline(timeout, clock))
+    }
+
+    /// Like [`Self::transmit_packet_with_timeout`], but instead of timing out, the transmission
+    /// is stopped with [`crate::Error::Cancelled`] once `cancel` returns `true`
+    pub fn transmit_packet_cancellable(
+        &self,
+        packet: &packet::Packet,
+        cancel: impl FnMut() -> bool,
+    ) -> crate::Result<()> {
+        self.transmit_packet_inner(packet, cancellation(cancel))
+    }
+
+    /// Transmits a packet, waiting indefinitely if needed
+    pub fn transmit_packet(&self, packet: &packet::Packet) -> crate::Result<()> {
+        self.transmit_packet_inner(packet, || Ok(()))
+    }
+
+    fn transmit_packet_inner(
+        &self,
+        packet: &packet::Packet,
+        poll: impl FnMut() -> crate::Result<()>,
+    ) -> crate::Result<()> {
+        let mut serialized = self.serialize_for_tx(packet)?;
+
+        // SAFETY: `serialized` lives on the stack until this function returns, and this function
+        // doesn't return until the radio has stopped reading from it
+        unsafe { self.set_packet_ptr(serialized.buf_mut().as_mut_ptr()) };
+
+        self.clear_event(Event::End);
+        reg_access::tasks::start(&self.radio);
+
+        self.wait_for_end(State::TX_IDLE, poll)
+    }
 }
 
-impl crate::Radio<Enabled<Receiver>> {
+impl<R: RadioRegisters> crate::Radio<Enabled<Receiver>, R> {
     impl_into_tx!();
 
     /// Enable a logical address for receiving. Multiple can be enabled at once by making use of
     /// [`Self::enable_rx_addresses`], which acts as a replacement for bitwise OR.
     pub fn enable_rx_address(&self, address: Address) -> &Self {
         let reg_value = reg_access::read_rx_address(&self.radio);
-        let new_reg_value = reg_value | address as u8;
+        let new_reg_value = reg_value | address.into_rx_address();
 
         reg_access::write_rx_address(&self.radio, new_reg_value);
 
@@ -425,7 +1002,7 @@ impl crate::Radio<Enabled<Receiver>> {
     /// [`Self::disable_rx_addresses`].
     pub fn disable_rx_address(&self, address: Address) -> &Self {
         let reg_value = reg_access::read_rx_address(&self.radio);
-        let mask = !(address as u8);
+        let mask = !address.into_rx_address();
         let new_reg_value = reg_value & mask;
 
         reg_access::write_rx_address(&self.radio, new_reg_value);
@@ -440,7 +1017,7 @@ impl crate::Radio<Enabled<Receiver>> {
         }
 
         // calculate the resulting register value so that only one write is needed
-        let mask = addresses.iter().fold(0, |acc, x| acc | *x as u8);
+        let mask = addresses.iter().fold(0, |acc, x| acc | x.into_rx_address());
 
         let reg_value = reg_access::read_rx_address(&self.radio);
         let new_reg_value = reg_value | mask;
@@ -457,7 +1034,7 @@ impl crate::Radio<Enabled<Receiver>> {
         }
 
         // calculate the resulting register value so that only one write is needed
-        let mask = addresses.iter().fold(0, |acc, x| acc | *x as u8);
+        let mask = addresses.iter().fold(0, |acc, x| acc | x.into_rx_address());
 
         let reg_value = reg_access::read_rx_address(&self.radio);
         let new_reg_value = reg_value & !(mask);
@@ -478,48 +1055,686 @@ impl crate::Radio<Enabled<Receiver>> {
         reg_access::read_rx_address(&self.radio)
     }
 
+    /// Program device address `slot` of the device address match unit. The slot isn't enabled
+    /// by this function, see [`Self::enable_device_address`]
+    ///
+    /// Returns [`crate::Error::ValueOutOfBounds`] if `slot` isn't lower than
+    /// [`MAX_DEVICE_ADDRESSES`]
+    pub fn set_device_address(&self, slot: u8, address: DeviceAddress) -> crate::Result<&Self> {
+        if slot >= MAX_DEVICE_ADDRESSES {
+            return Err(crate::Error::ValueOutOfBounds);
+        }
+
+        reg_access::write_dab(&self.radio, slot, address.base);
+        reg_access::write_dap(&self.radio, slot, address.prefix);
+        reg_access::write_dacnf_txadd(&self.radio, slot, address.tx_add);
+
+        Ok(self)
+    }
+
+    /// Get the device address programmed into `slot`, or `None` if `slot` isn't lower than
+    /// [`MAX_DEVICE_ADDRESSES`]
+    pub fn device_address(&self, slot: u8) -> Option<DeviceAddress> {
+        if slot >= MAX_DEVICE_ADDRESSES {
+            return None;
+        }
+
+        Some(DeviceAddress {
+            base: reg_access::read_dab(&self.radio, slot),
+            prefix: reg_access::read_dap(&self.radio, slot),
+            tx_add: reg_access::read_dacnf_txadd(&self.radio, slot),
+        })
+    }
+
+    /// Enable matching against device address `slot`
+    ///
+    /// Returns [`crate::Error::ValueOutOfBounds`] if `slot` isn't lower than
+    /// [`MAX_DEVICE_ADDRESSES`]
+    pub fn enable_device_address(&self, slot: u8) -> crate::Result<&Self> {
+        if slot >= MAX_DEVICE_ADDRESSES {
+            return Err(crate::Error::ValueOutOfBounds);
+        }
+
+        let enabled = reg_access::read_dacnf_ena(&self.radio);
+        reg_access::write_dacnf_ena(&self.radio, enabled | (1 << slot));
+
+        Ok(self)
+    }
+
+    /// Disable matching against device address `slot`
+    ///
+    /// Returns [`crate::Error::ValueOutOfBounds`] if `slot` isn't lower than
+    /// [`MAX_DEVICE_ADDRESSES`]
+    pub fn disable_device_address(&self, slot: u8) -> crate::Result<&Self> {
+        if slot >= MAX_DEVICE_ADDRESSES {
+            return Err(crate::Error::ValueOutOfBounds);
+        }
+
+        let enabled = reg_access::read_dacnf_ena(&self.radio);
+        reg_access::write_dacnf_ena(&self.radio, enabled & !(1 << slot));
+
+        Ok(self)
+    }
+
+    /// Disable matching against all device addresses
+    pub fn disable_all_device_addresses(&self) -> &Self {
+        reg_access::write_dacnf_ena(&self.radio, 0);
+
+        self
+    }
+
+    /// Get the device address slots that are enabled, one bit per slot
+    pub fn device_addresses(&self) -> BitMask<u8> {
+        reg_access::read_dacnf_ena(&self.radio)
+    }
+
     /// Receives a packet, waiting for `cycles` CPU cycles until returning [`crate::Error::TimedOut`]
-    pub fn receive_packet_with_timeout(&self, cycles: u32) -> crate::Result<packet::Packet> {
-        let mut p = packet::Packet::new_zeroed();
+    ///
+    /// If CRC is enabled (see [`Self::set_crc_config`]) and the received packet fails the check,
+    /// [`crate::Error::CrcMismatch`] is returned instead of the packet.
+    ///
+    /// Returns [`crate::Error::ValueOutOfBounds`] without receiving anything if the maximum
+    /// payload length the radio is set to could overflow the packet buffer (see
+    /// [`packet::PacketConfig::validate`]).
+    pub fn receive_packet_with_timeout(
+        &self,
+        cycles: u32,
+    ) -> crate::Result<packet::ReceivedPacket> {
+        self.receive_packet_inner(
+            cycle_deadline(cycles),
+            None::<fn() -> u64>,
+            None::<NoInspection>,
+        )
+    }
 
-        let lf_len = reg_access::read_lf_len(&self.radio);
-        let s0_len = reg_access::read_s0_len(&self.radio);
-        let s1_len = reg_access::read_s1_len(&self.radio);
+    /// Like [`Self::receive_packet_with_timeout`], but times out after `timeout` has passed on
+    /// `clock`. See [`clock`] for how accurate that is
+    pub fn receive_packet_within(
+        &self,
+        timeout: core::time::Duration,
+        clock: &mut impl clock::Clock,
+    ) -> crate::Result<packet::ReceivedPacket> {
+        self.receive_packet_inner(
+            time_deadline(timeout, clock),
+            None::<fn() -> u64>,
+            None::<NoInspection>,
+        )
+    }
 
-        p.set_lf_len(lf_len).set_s0_len(s0_len).set_s1_len(s1_len);
+    /// Like [`Self::receive_packet_with_timeout`], but instead of timing out, the reception is
+    /// stopped with [`crate::Error::Cancelled`] once `cancel` returns `true`
+    pub fn receive_packet_cancellable(
+        &self,
+        cancel: impl FnMut() -> bool,
+    ) -> crate::Result<packet::ReceivedPacket> {
+        self.receive_packet_inner(
+            cancellation(cancel),
+            None::<fn() -> u64>,
+            None::<NoInspection>,
+        )
+    }
+
+    /// Like [`Self::receive_packet_with_timeout`], but timestamps the packet with `clock` at the
+    /// moment its address is received. See [`packet::ReceivedPacket::timestamp`]
+    pub fn receive_packet_with_timestamp(
+        &self,
+        cycles: u32,
+        clock: impl FnMut() -> u64,
+    ) -> crate::Result<packet::ReceivedPacket> {
+        self.receive_packet_inner(cycle_deadline(cycles), Some(clock), None::<NoInspection>)
+    }
+
+    /// Like [`Self::receive_packet_with_timeout`], but lets `inspect` look at the packet once
+    /// `bits` bits following the address have been received, while the rest of it is still
+    /// arriving. If `inspect` returns `false`, the reception is aborted with
+    /// [`crate::Error::Rejected`].
+    ///
+    /// The packet passed to `inspect` only holds the bytes received so far - the `S0`, `LENGTH`
+    /// and `S1` fields are complete once `bits` covers them, the rest reads as zero. The bit
+    /// counter is started by the [`Shortcuts::ADDRESS_BCSTART`] shortcut, which is set for the
+    /// duration of the reception. If the packet ends before `inspect` gets to run, it is called
+    /// afterwards, so a rejected packet is never returned.
+    pub fn receive_packet_inspecting(
+        &self,
+        cycles: u32,
+        bits: u32,
+        inspect: impl FnMut(&packet::Packet) -> bool,
+    ) -> crate::Result<packet::ReceivedPacket> {
+        let previous = self.shortcuts();
+        self.set_bit_counter_compare(bits)
+            .set_shortcuts(previous | Shortcuts::ADDRESS_BCSTART);
+
+        let r = self.receive_packet_inner(
+            cycle_deadline(cycles),
+            None::<fn() -> u64>,
+            Some((bits, inspect)),
+        );
+
+        self.stop_bit_counter().set_shortcuts(previous);
+
+        r
+    }
+
+    fn receive_packet_inner(
+        &self,
+        mut poll: impl FnMut() -> crate::Result<()>,
+        mut clock: Option<impl FnMut() -> u64>,
+        mut inspection: Option<(u32, impl FnMut(&packet::Packet) -> bool)>,
+    ) -> crate::Result<packet::ReceivedPacket> {
+        let mut p = self.new_rx_packet()?;
+        // what has been received so far is copied here for inspection
+        let mut partial = p.clone();
 
         let buf_ptr = p.buf_mut_ptr();
         reg_access::set_packet_ptr(&self.radio, buf_ptr);
 
+        self.clear_event(Event::Address);
+        self.clear_event(Event::RSSIEnd);
+        self.clear_event(Event::DevMatch);
+        self.clear_event(Event::BCMatch);
+        self.clear_event(Event::End);
         reg_access::tasks::start(&self.radio);
-        self.wait_for_state_cycles(State::RX_IDLE, cycles)?;
 
-        Ok(p)
-    }
+        let mut timestamp = None;
+        let mut address_received = false;
+        let mut verdict = None;
 
-    /// Receives a packet, waiting indefinitely if needed
-    pub fn receive_packet(&self) -> crate::Result<packet::Packet> {
-        loop {
-            let r = self.receive_packet_with_timeout(u32::MAX);
-            if Err(crate::Error::TimedOut) == r {
-                continue;
+        let mut inspect = || {
+            let (bits, inspect) = inspection.as_mut()?;
+            if !self.event_occurred(Event::BCMatch) {
+                return None;
             }
 
-            r?;
+            let len = partial.received_len(*bits);
+            // SAFETY: both buffers are packet buffers, and `len` is capped at their size
+            unsafe { partial.copy_from_volatile(buf_ptr, len) };
+
+            Some(inspect(&partial))
+        };
+
+        let r = self.wait_for_end(State::RX_IDLE, || {
+            // sample the RSSI and the timestamp as soon as the frame starts
+            if !address_received && self.event_occurred(Event::Address) {
+                address_received = true;
+
+                reg_access::tasks::rssistart(&self.radio);
+                timestamp = clock.as_mut().map(|c| c());
+            }
+
+            if verdict.is_none() {
+                verdict = inspect();
+            }
+
+            if verdict == Some(false) {
+                return Err(crate::Error::Rejected);
+            }
+
+            poll()
+        });
+
+        reg_access::tasks::rssistop(&self.radio);
+
+        if r.is_ok() && verdict.is_none() {
+            verdict = inspect();
+        }
+        if verdict == Some(false) {
+            return Err(crate::Error::Rejected);
+        }
+        r?;
+
+        self.finish_rx_packet(p, timestamp)
+    }
+
+    /// Samples the received signal strength, returning it in dBm
+    ///
+    /// The receiver is started for the duration of the measurement if it's idle. Returns
+    /// [`crate::Error::ValueOutOfBounds`] if the maximum payload length the radio is set to could
+    /// overflow a packet buffer, as a packet may be received in the meantime.
+    pub fn sample_rssi(&self) -> crate::Result<i8> {
+        self.with_receiver_running(|| -(self.sample_rssi_raw() as i8))
+    }
+
+    /// Measures the signal levels on each channel in `range`, taking `dwell` samples per channel,
+    /// and writes them into `results`. Returns the number of channels scanned
+    ///
+    /// The radio is ramped up again on every channel, since a frequency change only takes effect
+    /// then. Afterwards, the original frequency is restored.
+    ///
+    /// Returns [`crate::Error::ValueOutOfBounds`] if `results` can't hold all of the channels in
+    /// `range`, or for the same reasons as [`Self::sample_rssi`].
+    pub fn energy_scan(
+        &self,
+        range: core::ops::RangeInclusive<Frequency>,
+        dwell: u32,
+        results: &mut [ChannelEnergy],
+    ) -> crate::Result<usize> {
+        let (start, end) = (range.start().0, range.end().0);
+        let channel_count = if end >= start { end - start + 1 } else { 0 };
+        if channel_count as usize > results.len() {
+            return Err(crate::Error::ValueOutOfBounds);
+        }
+
+        let channels = (start..=end).map(Frequency);
+
+        let original = reg_access::read_frequency(&self.radio);
+        let r = self.scan_channels(channels, dwell, results);
+        self.retune(original);
+
+        r
+    }
+
+    /// Does the work of [`Self::energy_scan`], leaving the radio on the last channel scanned
+    fn scan_channels(
+        &self,
+        channels: impl Iterator<Item = Frequency>,
+        dwell: u32,
+        results: &mut [ChannelEnergy],
+    ) -> crate::Result<usize> {
+        let mut scanned = 0;
+        for (freq, result) in channels.zip(results.iter_mut()) {
+            self.retune(freq.0);
+
+            let (peak, sum) = self.with_receiver_running(|| {
+                (0..dwell.max(1)).fold((u8::MAX, 0u32), |(peak, sum), _| {
+                    let sample = self.sample_rssi_raw();
+                    (peak.min(sample), sum + u32::from(sample))
+                })
+            })?;
+
+            *result = ChannelEnergy {
+                frequency: freq,
+                peak: -(peak as i8),
+                average: -((sum / dwell.max(1)) as i8),
+            };
+            scanned += 1;
+        }
+
+        Ok(scanned)
+    }
+
+    /// Switches the radio to `freq` by ramping it up again
+    fn retune(&self, freq: u32) {
+        reg_access::disable(&self.radio);
+        self.wait_for_state(State::DISABLED);
+
+        reg_access::write_frequency(&self.radio, freq);
+
+        reg_access::enable_rx(&self.radio);
+        self.wait_for_state(State::RX_IDLE);
+    }
+
+    /// Takes a single RSSI sample. The receiver has to be running
+    fn sample_rssi_raw(&self) -> u8 {
+        self.clear_event(Event::RSSIEnd);
+        reg_access::tasks::rssistart(&self.radio);
+
+        self.wait_for_event(Event::RSSIEnd);
+
+        reg_access::tasks::rssistop(&self.radio);
+        reg_access::read_rssi_sample(&self.radio)
+    }
+
+    /// Runs `f` with the receiver started, starting and stopping it if it's idle
+    ///
+    /// The receiver writes into a scratch buffer in the meantime, in case a packet arrives.
+    fn with_receiver_running<O>(&self, f: impl FnOnce() -> O) -> crate::Result<O> {
+        if self.get_state()? != State::RX_IDLE {
+            return Ok(f());
         }
+
+        let config_len = reg_access::read_lf_len(&self.radio).bytes()
+            + reg_access::read_s0_len(&self.radio).bytes()
+            + reg_access::read_s1_len(&self.radio).bytes()
+            + usize::from(reg_access::read_maxlen(&self.radio));
+        if config_len > packet::MAX_IN_MEMORY_PACKET_LENGTH {
+            return Err(crate::Error::ValueOutOfBounds);
+        }
+
+        let mut scratch: packet::PacketBuffer = [0; _];
+        reg_access::set_packet_ptr(&self.radio, scratch.as_mut_ptr());
+
+        reg_access::tasks::start(&self.radio);
+        self.wait_for_state(State::RX);
+
+        let r = f();
+
+        reg_access::tasks::stop(&self.radio);
+        self.wait_for_state(State::RX_IDLE);
+
+        Ok(r)
+    }
+
+    /// Receives a packet, waiting indefinitely if needed
+    pub fn receive_packet(&self) -> crate::Result<packet::ReceivedPacket> {
+        self.receive_packet_inner(|| Ok(()), None::<fn() -> u64>, None::<NoInspection>)
     }
 }
 
+/// Signal levels measured on one channel by [`Radio::energy_scan`]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ChannelEnergy {
+    /// The scanned channel
+    pub frequency: Frequency,
+    /// The strongest sample, in dBm
+    pub peak: i8,
+    /// The average of the samples, in dBm
+    pub average: i8,
+}
+
 pub use nrf51_pac::radio::state::STATE_A as State;
 
 use crate::packet::{LengthFieldLength, S0FieldLength, S1FieldLength};
 
-impl<T> crate::Radio<T> {
+bitflags::bitflags! {
+    /// Shortcuts between radio events and tasks. When a shortcut is enabled, the radio triggers
+    /// the task on its own as soon as the event occurs, without waiting for the CPU
+    #[derive(Copy, Clone, Eq, PartialEq, Debug)]
+    pub struct Shortcuts: u32 {
+        /// Start the radio once it has ramped up
+        const READY_START = 1 << 0;
+        /// Disable the radio once a packet has been sent or received
+        const END_DISABLE = 1 << 1;
+        /// Ramp up the transmitter once the radio has been disabled
+        const DISABLED_TXEN = 1 << 2;
+        /// Ramp up the receiver once the radio has been disabled
+        const DISABLED_RXEN = 1 << 3;
+        /// Start sampling the RSSI once an address has been received
+        const ADDRESS_RSSISTART = 1 << 4;
+        /// Start the radio again once a packet has been sent or received
+        const END_START = 1 << 5;
+        /// Start the bit counter once an address has been sent or received
+        const ADDRESS_BCSTART = 1 << 6;
+        /// Stop sampling the RSSI once the radio has been disabled
+        const DISABLED_RSSISTOP = 1 << 8;
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Shortcuts {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "Shortcuts({=u32:#b})", self.bits());
+    }
+}
+
+impl<R: RadioRegisters> crate::Radio<Disabled, R> {
+    /// Ramps up the transmitter, sends `packet` and disables the radio again, letting shortcuts
+    /// do all of the state transitions. Waits for `cycles` CPU cycles until returning
+    /// [`crate::Error::TimedOut`]
+    ///
+    /// The shortcuts the radio was set to are restored afterwards. Apart from that, this behaves
+    /// like [`Radio::transmit_packet_with_timeout`].
+    pub fn transmit_packet_with_shortcuts(
+        &self,
+        packet: &packet::Packet,
+        cycles: u32,
+    ) -> crate::Result<()> {
+        self.transmit_packet_with_shortcuts_inner(packet, cycle_deadline(cycles))
+    }
+
+    /// Like [`Self::transmit_packet_with_shortcuts`], but times out after `timeout` has passed
+    /// on `clock`. See [`clock`] for how accurate that is
+    pub fn transmit_packet_with_shortcuts_within(
+        &self,
+        packet: &packet::Packet,
+        timeout: core::time::Duration,
+        clock: &mut impl clock::Clock,
+    ) -> crate::Result<()> {
+        self.transmit_packet_with_shortcuts_inner(packet, time_deadline(timeout, clock))
+    }
+
+    fn transmit_packet_with_shortcuts_inner(
+        &self,
+        packet: &packet::Packet,
+        deadline: impl FnMut() -> crate::Result<()>,
+    ) -> crate::Result<()> {
+        let mut serialized = self.serialize_for_tx(packet)?;
+
+        // SAFETY: `serialized` lives on the stack until this function returns, and
+        // `run_with_shortcuts` doesn't return until the radio is disabled
+        unsafe { self.set_packet_ptr(serialized.buf_mut().as_mut_ptr()) };
+
+        self.run_with_shortcuts(
+            Shortcuts::READY_START | Shortcuts::END_DISABLE,
+            reg_access::enable_tx,
+            deadline,
+        )
+    }
+
+    /// Ramps up the receiver, receives a packet and disables the radio again, letting shortcuts
+    /// do all of the state transitions, including sampling the RSSI. Waits for `cycles` CPU
+    /// cycles until returning [`crate::Error::TimedOut`]
+    ///
+    /// The shortcuts the radio was set to are restored afterwards. Apart from that, this behaves
+    /// like [`Radio::receive_packet_with_timeout`].
+    pub fn receive_packet_with_shortcuts(
+        &self,
+        cycles: u32,
+    ) -> crate::Result<packet::ReceivedPacket> {
+        self.receive_packet_with_shortcuts_inner(cycle_deadline(cycles))
+    }
+
+    /// Like [`Self::receive_packet_with_shortcuts`], but times out after `timeout` has passed on
+    /// `clock`. See [`clock`] for how accurate that is
+    pub fn receive_packet_with_shortcuts_within(
+        &self,
+        timeout: core::time::Duration,
+        clock: &mut impl clock::Clock,
+    ) -> crate::Result<packet::ReceivedPacket> {
+        self.receive_packet_with_shortcuts_inner(time_deadline(timeout, clock))
+    }
+
+    fn receive_packet_with_shortcuts_inner(
+        &self,
+        deadline: impl FnMut() -> crate::Result<()>,
+    ) -> crate::Result<packet::ReceivedPacket> {
+        let mut p = self.new_rx_packet()?;
+
+        // SAFETY: `p` lives on the stack until this function returns, and `run_with_shortcuts`
+        // doesn't return until the radio is disabled
+        unsafe { self.set_packet_ptr(p.buf_mut_ptr()) };
+
+        self.clear_event(Event::RSSIEnd);
+        self.clear_event(Event::DevMatch);
+
+        self.run_with_shortcuts(
+            Shortcuts::READY_START
+                | Shortcuts::END_DISABLE
+                | Shortcuts::ADDRESS_RSSISTART
+                | Shortcuts::DISABLED_RSSISTOP,
+            reg_access::enable_rx,
+            deadline,
+        )?;
+
+        self.finish_rx_packet(p, None)
+    }
+
+    /// Sets `shortcuts`, triggers `enable` and waits until the radio gets disabled again, calling
+    /// `deadline` all the while
+    fn run_with_shortcuts(
+        &self,
+        shortcuts: Shortcuts,
+        enable: fn(&R),
+        mut deadline: impl FnMut() -> crate::Result<()>,
+    ) -> crate::Result<()> {
+        let previous = self.shortcuts();
+        self.set_shortcuts(shortcuts);
+
+        self.clear_event(Event::Disabled);
+        enable(&self.radio);
+
+        let mut r = Ok(());
+        while !self.event_occurred(Event::Disabled) {
+            r = deadline();
+            if r.is_err() {
+                break;
+            }
+        }
+        if r.is_err() {
+            reg_access::disable(&self.radio);
+            self.wait_for_state(State::DISABLED);
+        }
+
+        self.set_shortcuts(previous);
+
+        r
+    }
+}
+
+impl<T, R: RadioRegisters> crate::Radio<T, R> {
+    /// Set the shortcuts between events and tasks, replacing the ones that were set before
+    pub fn set_shortcuts(&self, shortcuts: Shortcuts) -> &Self {
+        reg_access::write_shortcuts(&self.radio, shortcuts.bits());
+
+        self
+    }
+
+    /// Get the shortcuts the radio is set to
+    pub fn shortcuts(&self) -> Shortcuts {
+        Shortcuts::from_bits_truncate(reg_access::read_shortcuts(&self.radio))
+    }
+
+    /// Set the pointer to a packet which should be sent, or set the pointer to a packet buffer to
+    /// which a received packet should be written
+    ///
+    /// # Safety
+    ///
+    /// The pointee MUST be a buffer or otherwise writable memory location.
+    ///
+    /// The pointer MUST be aligned and MUST NOT be dangling, otherwise **UB will be invoked.**
+    unsafe fn set_packet_ptr<P>(&self, ptr: *mut P) -> &Self {
+        reg_access::set_packet_ptr(&self.radio, ptr);
+
+        self
+    }
+
+    /// Checks the packet against the radio configuration and serializes it
+    fn serialize_for_tx(
+        &self,
+        packet: &packet::Packet,
+    ) -> crate::Result<packet::SerializedPacketBuffer> {
+        if packet.payload_len() > usize::from(reg_access::read_maxlen(&self.radio)) {
+            return Err(crate::Error::ValueOutOfBounds);
+        }
+
+        packet.serialize()
+    }
+
+    /// Constructs an empty packet matching the radio configuration, for the radio to receive into
+    fn new_rx_packet(&self) -> crate::Result<packet::Packet> {
+        let mut p = packet::Packet::new_zeroed();
+
+        let lf_len = reg_access::read_lf_len(&self.radio);
+        let s0_len = reg_access::read_s0_len(&self.radio);
+        let s1_len = reg_access::read_s1_len(&self.radio);
+
+        let endian = reg_access::get_endianness(&self.radio);
+        let statlen = reg_access::read_statlen(&self.radio);
+        let maxlen = reg_access::read_maxlen(&self.radio);
+
+        p.set_lf_len(lf_len)
+            .set_s0_len(s0_len)
+            .set_s1_len(s1_len)
+            .set_endianness(endian)
+            .set_payload_lens(statlen, maxlen);
+
+        // the radio would write past the end of the buffer
+        if p.header_len() + usize::from(maxlen) > packet::MAX_IN_MEMORY_PACKET_LENGTH {
+            return Err(crate::Error::ValueOutOfBounds);
+        }
+
+        Ok(p)
+    }
+
+    /// Checks the CRC of a packet which has just been received and collects the information
+    /// about its reception
+    fn finish_rx_packet(
+        &self,
+        p: packet::Packet,
+        timestamp: Option<u64>,
+    ) -> crate::Result<packet::ReceivedPacket> {
+        let rssi = self
+            .event_occurred(Event::RSSIEnd)
+            .then(|| -(reg_access::read_rssi_sample(&self.radio) as i8));
+
+        let crc = if reg_access::read_crc_len(&self.radio) == CrcLength::DISABLED {
+            None
+        } else if reg_access::crc_ok(&self.radio) {
+            Some(reg_access::read_rx_crc(&self.radio))
+        } else {
+            return Err(crate::Error::CrcMismatch);
+        };
+
+        let address = Address::from_repr(reg_access::read_rx_match(&self.radio))
+            .expect("invalid RXMATCH value; if you're seeing this it's a bug");
+
+        let device_address = self
+            .event_occurred(Event::DevMatch)
+            .then(|| reg_access::read_dai(&self.radio));
+
+        Ok(packet::ReceivedPacket {
+            packet: p,
+            address,
+            crc,
+            rssi,
+            timestamp,
+            device_address,
+        })
+    }
+
     /// Get the state which the radio is currently in
     pub fn get_state(&self) -> crate::Result<State> {
         reg_access::get_state(&self.radio).ok_or(crate::Error::UnknownState)
     }
 
+    /// Checks whether `event` has been raised
+    pub fn event_occurred(&self, event: Event) -> bool {
+        reg_access::events::occurred(&self.radio, event)
+    }
+
+    /// Clears `event`, so that it can be waited on again
+    pub fn clear_event(&self, event: Event) -> &Self {
+        reg_access::events::clear(&self.radio, event);
+
+        self
+    }
+
+    /// Wait until `event` is raised. Break out of the function after `cycles`, returning
+    /// [`crate::Error::TimedOut`]. The event isn't cleared
+    pub fn wait_for_event_cycles(&self, event: Event, cycles: u32) -> crate::Result<()> {
+        for _ in 0..cycles {
+            if self.event_occurred(event) {
+                return Ok(());
+            }
+        }
+
+        Err(crate::Error::TimedOut)
+    }
+
+    /// Like [`Self::wait_for_event_cycles`], but times out after `timeout` has passed on `clock`.
+    /// See [`clock`] for how accurate that is
+    pub fn wait_for_event_within(
+        &self,
+        event: Event,
+        timeout: core::time::Duration,
+        clock: &mut impl clock::Clock,
+    ) -> crate::Result<()> {
+        let mut deadline = time_deadline(timeout, clock);
+
+        while !self.event_occurred(event) {
+            deadline()?;
+        }
+
+        Ok(())
+    }
+
+    /// Wait until `event` is raised. Be careful not to deadlock your program this way
+    pub fn wait_for_event(&self, event: Event) {
+        while !self.event_occurred(event) {
+            core::hint::spin_loop();
+        }
+    }
+
     /// Wait until radio goes into state. Break out of the function after `cycles`, returning [`crate::Error::TimedOut`].
     ///
     /// # Safety
@@ -535,6 +1750,27 @@ impl<T> crate::Radio<T> {
         Err(crate::Error::TimedOut)
     }
 
+    /// Like [`Self::wait_for_state_cycles`], but times out after `timeout` has passed on `clock`.
+    /// See [`clock`] for how accurate that is
+    ///
+    /// # Safety
+    ///
+    /// This function panics if the radio is in an invalid state
+    pub fn wait_for_state_within(
+        &self,
+        state: State,
+        timeout: core::time::Duration,
+        clock: &mut impl clock::Clock,
+    ) -> crate::Result<()> {
+        let mut deadline = time_deadline(timeout, clock);
+
+        while self.get_state().expect("The radio is in an invalid state") != state {
+            deadline()?;
+        }
+
+        Ok(())
+    }
+
     /// Wait until radio goes into `state`. Be careful not to deadlock your program this way.
     ///
     /// # Safety
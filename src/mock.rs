@@ -0,0 +1,289 @@
+//! An in-memory stand-in for the RADIO peripheral, so that the driver can be run on the host
+//!
+//! [`MockRadio`] keeps the registers in memory and reacts to tasks the way the hardware does,
+//! except that everything happens instantly: ramp-up is immediate, and a transmitted packet is
+//! done as soon as `START` is triggered. A received packet has to be queued with
+//! [`MockRadio::queue_rx`] before the receiver is started, or it will sit in [`State::RX`]
+//! until it's stopped.
+//!
+//! ```ignore
+//! let mock = MockRadio::new();
+//! let radio = Radio::new_zeroed(&mock).into_transmitter();
+//!
+//! radio.transmit_packet(&packet)?;
+//! assert_eq!(mock.transmitted().unwrap().bytes(), &[3, 1, 2, 3]);
+//! ```
+
+use core::cell::Cell;
+
+use crate::{
+    Address, Shortcuts, State,
+    packet::{MAX_IN_MEMORY_PACKET_LENGTH, PacketBuffer},
+    registers::{RadioRegisters, Register},
+};
+
+/// Number of words in the RADIO register block
+const REGISTER_COUNT: usize = 0x1000 / 4;
+
+/// A packet as it's laid out in RAM, together with the way it went over the air
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Frame {
+    buffer: PacketBuffer,
+    len: usize,
+    /// The logical address the packet was sent to
+    pub address: Address,
+    /// Whether the packet arrived with a correct CRC
+    pub crc_ok: bool,
+    /// The received signal strength, in -dBm
+    pub rssi: u8,
+}
+
+impl Frame {
+    /// Constructs a frame to logical address [`Address::A`] with a correct CRC, or returns
+    /// `None` if `bytes` don't fit into a packet buffer
+    pub fn new(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() > MAX_IN_MEMORY_PACKET_LENGTH {
+            return None;
+        }
+
+        let mut buffer = [0; MAX_IN_MEMORY_PACKET_LENGTH];
+        buffer[..bytes.len()].copy_from_slice(bytes);
+
+        Some(Self {
+            buffer,
+            len: bytes.len(),
+            address: Address::A,
+            crc_ok: true,
+            rssi: 0,
+        })
+    }
+
+    /// The packet, as it's laid out in RAM (S0, LENGTH, S1 and the payload)
+    pub fn bytes(&self) -> &[u8] {
+        &self.buffer[..self.len]
+    }
+}
+
+/// An in-memory RADIO peripheral. See the [module documentation](self)
+pub struct MockRadio {
+    registers: [Cell<u32>; REGISTER_COUNT],
+    packet_ptr: Cell<*mut u8>,
+    rx_queue: Cell<Option<Frame>>,
+    transmitted: Cell<Option<Frame>>,
+}
+
+impl Default for MockRadio {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockRadio {
+    /// Constructs a radio with all registers set to zero
+    pub fn new() -> Self {
+        Self {
+            registers: [const { Cell::new(0) }; REGISTER_COUNT],
+            packet_ptr: Cell::new(core::ptr::null_mut()),
+            rx_queue: Cell::new(None),
+            transmitted: Cell::new(None),
+        }
+    }
+
+    /// Queues `frame` to be received the next time the receiver is started
+    pub fn queue_rx(&self, frame: Frame) {
+        self.rx_queue.set(Some(frame));
+    }
+
+    /// The last frame that was transmitted
+    pub fn transmitted(&self) -> Option<Frame> {
+        self.transmitted.get()
+    }
+
+    /// The current state of the radio
+    pub fn state(&self) -> Option<State> {
+        crate::reg_access::get_state(self)
+    }
+
+    fn set_rssi(&self, rssi: u8) {
+        self.set(Register::RssiSample, (rssi & 0x7F).into());
+    }
+
+    fn get(&self, reg: Register) -> u32 {
+        self.registers[reg.offset() / 4].get()
+    }
+
+    fn set(&self, reg: Register, value: u32) {
+        self.registers[reg.offset() / 4].set(value);
+    }
+
+    fn shortcuts(&self) -> Shortcuts {
+        Shortcuts::from_bits_truncate(self.get(Register::Shorts))
+    }
+
+    fn set_state(&self, state: State) {
+        self.set(Register::State, u8::from(state).into());
+    }
+
+    fn raise(&self, event: Register) {
+        self.set(event, 1);
+    }
+
+    /// Number of bytes the packet at `ptr` takes up, according to the packet configuration
+    ///
+    /// # Safety
+    ///
+    /// `ptr` has to point to a valid packet buffer
+    unsafe fn packet_len(&self, ptr: *const u8) -> usize {
+        // SAFETY: guaranteed by the caller
+        let buf = unsafe { core::slice::from_raw_parts(ptr, MAX_IN_MEMORY_PACKET_LENGTH) };
+
+        crate::reg_access::read_packet_config(self).in_memory_len(buf)
+    }
+
+    fn start(&self) {
+        match self.state() {
+            Some(State::TX_IDLE) => self.transmit(),
+            Some(State::RX_IDLE) => {
+                self.set_state(State::RX);
+                self.receive();
+            }
+            _ => {}
+        }
+    }
+
+    fn transmit(&self) {
+        // SAFETY: the driver points PACKETPTR to a packet buffer before starting
+        let len = unsafe { self.packet_len(self.packet_ptr.get()) };
+        let mut buffer = [0; MAX_IN_MEMORY_PACKET_LENGTH];
+        // SAFETY: same as above
+        unsafe { core::ptr::copy_nonoverlapping(self.packet_ptr.get(), buffer.as_mut_ptr(), len) };
+
+        let address =
+            Address::from_repr(self.get(Register::TxAddress) as u8 & 7).unwrap_or(Address::A);
+
+        self.transmitted.set(Some(Frame {
+            buffer,
+            len,
+            address,
+            crc_ok: true,
+            rssi: 0,
+        }));
+
+        self.raise(Register::EventsAddress);
+        self.raise(Register::EventsPayload);
+        self.end();
+    }
+
+    fn receive(&self) {
+        let Some(frame) = self.rx_queue.get() else {
+            return;
+        };
+
+        // the receiver doesn't listen to this address
+        if self.get(Register::RxAddresses) & (1 << frame.address as u8) == 0 {
+            return;
+        }
+        self.rx_queue.set(None);
+
+        self.set(Register::RxMatch, frame.address as u8 as u32);
+        self.set_rssi(frame.rssi);
+        self.raise(Register::EventsAddress);
+        if self.shortcuts().contains(Shortcuts::ADDRESS_RSSISTART) {
+            self.raise(Register::EventsRssiEnd);
+        }
+
+        // SAFETY: the frame's buffer is a whole packet buffer
+        let len = unsafe { self.packet_len(frame.buffer.as_ptr()) }.min(frame.len);
+        // SAFETY: the driver points PACKETPTR to a packet buffer before starting
+        unsafe {
+            core::ptr::copy_nonoverlapping(frame.buffer.as_ptr(), self.packet_ptr.get(), len)
+        };
+
+        self.set(Register::CrcStatus, frame.crc_ok.into());
+        self.raise(Register::EventsPayload);
+        self.set_state(State::RX_IDLE);
+        self.end();
+    }
+
+    /// Finishes the packet in flight
+    fn end(&self) {
+        self.raise(Register::EventsEnd);
+
+        let shortcuts = self.shortcuts();
+        if shortcuts.contains(Shortcuts::END_DISABLE) {
+            self.disable();
+        } else if shortcuts.contains(Shortcuts::END_START) {
+            self.start();
+        }
+    }
+
+    fn enable(&self, idle: State) {
+        if self.state() != Some(State::DISABLED) {
+            return;
+        }
+
+        self.set_state(idle);
+        self.raise(Register::EventsReady);
+        if self.shortcuts().contains(Shortcuts::READY_START) {
+            self.start();
+        }
+    }
+
+    fn disable(&self) {
+        self.set_state(State::DISABLED);
+        self.raise(Register::EventsDisabled);
+
+        let shortcuts = self.shortcuts();
+        if shortcuts.contains(Shortcuts::DISABLED_TXEN) {
+            self.enable(State::TX_IDLE);
+        } else if shortcuts.contains(Shortcuts::DISABLED_RXEN) {
+            self.enable(State::RX_IDLE);
+        }
+    }
+
+    fn stop(&self) {
+        match self.state() {
+            Some(State::RX) => self.set_state(State::RX_IDLE),
+            Some(State::TX) => self.set_state(State::TX_IDLE),
+            _ => {}
+        }
+    }
+}
+
+impl RadioRegisters for MockRadio {
+    fn read(&self, reg: Register) -> u32 {
+        match reg {
+            Register::IntEnClr => self.get(Register::IntEnSet),
+            _ => self.get(reg),
+        }
+    }
+
+    fn write(&self, reg: Register, value: u32) {
+        match reg {
+            Register::TasksTxEn => self.enable(State::TX_IDLE),
+            Register::TasksRxEn => self.enable(State::RX_IDLE),
+            Register::TasksStart => self.start(),
+            Register::TasksStop => self.stop(),
+            Register::TasksDisable => self.disable(),
+            Register::TasksRssiStart => self.raise(Register::EventsRssiEnd),
+            Register::TasksRssiStop | Register::TasksBcStart | Register::TasksBcStop => {}
+            Register::IntEnSet => self.set(reg, self.get(reg) | value),
+            Register::IntEnClr => {
+                self.set(Register::IntEnSet, self.get(Register::IntEnSet) & !value)
+            }
+            Register::PacketPtr => self.packet_ptr.set(value as usize as *mut u8),
+            Register::CrcStatus
+            | Register::RxMatch
+            | Register::RxCrc
+            | Register::Dai
+            | Register::RssiSample
+            | Register::State => {}
+            _ => self.set(reg, value),
+        }
+    }
+
+    fn set_packet_ptr(&self, ptr: *mut u8) {
+        self.packet_ptr.set(ptr);
+    }
+}
@@ -0,0 +1,293 @@
+//! Talking to nRF24L01+ radios
+//!
+//! The nRF24L01+ is configured through its own registers, which map onto the nRF51's in ways that
+//! aren't obvious. [`Config`] holds the settings both ends have to agree on, named and encoded
+//! like the nRF24L01+ registers they come from, and [`Radio::apply_nrf24`] sets the radio up to
+//! match them:
+//!
+//! | nRF24L01+                             | nRF51                                                |
+//! |---------------------------------------|------------------------------------------------------|
+//! | `RF_CH`                               | [`Frequency`], 2400 MHz + `RF_CH`                    |
+//! | `RF_DR_LOW` and `RF_DR_HIGH`          | [`Mode`]                                             |
+//! | `SETUP_AW`                            | [`BaseAddressLength`], one byte less                 |
+//! | `EN_CRC` and `CRCO`                   | [`CrcConfig`], over the address, initialised to ones |
+//! | `EN_DPL` and `RX_PW_Px`               | [`PacketConfig`], big endian                         |
+//!
+//! Addresses are written to the nRF24L01+ least significant byte first, and go over the air most
+//! significant bit first. The least significant byte is the one pipes 1 to 5 have to themselves,
+//! so it becomes the prefix - [`physical_address`] does the conversion.
+//!
+//! Both layouts keep the 9-bit packet control field of Enhanced ShockBurst, so
+//! [`crate::esb::PacketControl`] applies to them. With dynamic payload length, the configuration
+//! is the one of the `EsbDynamic` presets, so [`crate::esb`] sends the same packets as an
+//! nRF24L01+ with auto acknowledgement. Its acknowledgements are sent by the CPU rather than
+//! after `TIFS` though (see [`crate::esb::Prx`]), and whether they arrive within the auto
+//! retransmit delay of an nRF24L01+ transmitter - `ARD`, 250 µs after reset - hasn't been
+//! verified, so a longer delay is the safer choice. With a static payload length,
+//! acknowledgements are empty while the layout expects every packet to be [`Config::payload`]
+//! bytes long, so only packets sent with `NO_ACK` can be exchanged.
+
+use crate::{
+    Address, BaseAddressLength, CrcConfig, CrcLength, Enabled, Endianness, FREQUENCY_OFFSET,
+    Frequency, Mode, PhysicalAddress, Radio, RadioRegisters,
+    packet::{LengthFieldLength, PacketConfig, S0FieldLength, S1FieldLength},
+    preset::Preset,
+};
+
+/// Maximum payload length of the nRF24L01+
+pub const MAX_PAYLOAD_LENGTH: u8 = 32;
+
+/// Data rate (`RF_DR_LOW` and `RF_DR_HIGH` in `RF_SETUP`)
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DataRate {
+    /// 250 kbit/s
+    Kbit250,
+    /// 1 Mbit/s
+    Mbit1,
+    /// 2 Mbit/s, the nRF24L01+'s reset value
+    Mbit2,
+}
+
+impl DataRate {
+    /// Decodes the value of the `RF_SETUP` register. Returns `None` for the reserved combination
+    /// of `RF_DR_LOW` and `RF_DR_HIGH`
+    pub fn from_rf_setup(rf_setup: u8) -> Option<Self> {
+        match (rf_setup & (1 << 5) != 0, rf_setup & (1 << 3) != 0) {
+            (true, false) => Some(Self::Kbit250),
+            (false, false) => Some(Self::Mbit1),
+            (false, true) => Some(Self::Mbit2),
+            (true, true) => None,
+        }
+    }
+
+    /// The nRF51 mode with the same data rate
+    pub fn mode(&self) -> Mode {
+        match self {
+            Self::Kbit250 => Mode::NRF_250KBIT,
+            Self::Mbit1 => Mode::NRF_1MBIT,
+            Self::Mbit2 => Mode::NRF_2MBIT,
+        }
+    }
+}
+
+/// Address width (`SETUP_AW`)
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AddressWidth {
+    /// 3 bytes
+    Three,
+    /// 4 bytes
+    Four,
+    /// 5 bytes, the nRF24L01+'s reset value
+    Five,
+}
+
+impl AddressWidth {
+    /// Decodes the value of the `SETUP_AW` register. Returns `None` for the illegal value `0`
+    pub fn from_setup_aw(setup_aw: u8) -> Option<Self> {
+        match setup_aw & 0b11 {
+            1 => Some(Self::Three),
+            2 => Some(Self::Four),
+            3 => Some(Self::Five),
+            _ => None,
+        }
+    }
+
+    /// The width in bytes
+    pub fn bytes(&self) -> u8 {
+        match self {
+            Self::Three => 3,
+            Self::Four => 4,
+            Self::Five => 5,
+        }
+    }
+
+    /// The base address length which makes up this width together with the prefix
+    pub fn base_address_length(&self) -> BaseAddressLength {
+        BaseAddressLength(self.bytes() - 1)
+    }
+}
+
+/// CRC length (`EN_CRC` and `CRCO` in `CONFIG`)
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Crc {
+    /// No CRC. The nRF24L01+ forces the CRC on as long as auto acknowledgement is enabled
+    Disabled,
+    /// 1 byte, `x^8 + x^2 + x + 1`
+    OneByte,
+    /// 2 bytes, `x^16 + x^12 + x^5 + 1`
+    TwoBytes,
+}
+
+impl Crc {
+    /// Decodes the value of the `CONFIG` register
+    pub fn from_config(config: u8) -> Self {
+        match (config & (1 << 3) != 0, config & (1 << 2) != 0) {
+            (false, _) => Self::Disabled,
+            (true, false) => Self::OneByte,
+            (true, true) => Self::TwoBytes,
+        }
+    }
+
+    /// The nRF51 CRC configuration calculating the same CRC
+    pub fn crc_config(&self) -> CrcConfig {
+        match self {
+            Self::Disabled => CrcConfig {
+                len: CrcLength::DISABLED,
+                skip_address: false,
+                polynomial: 0,
+                init: 0,
+            },
+            Self::OneByte => CrcConfig {
+                len: CrcLength::ONE,
+                skip_address: false,
+                polynomial: 0x07,
+                init: 0xFF,
+            },
+            Self::TwoBytes => CrcConfig {
+                len: CrcLength::TWO,
+                skip_address: false,
+                polynomial: 0x1021,
+                init: 0xFFFF,
+            },
+        }
+    }
+}
+
+/// How the length of the payload is determined (`EN_DPL` in `FEATURE`, `RX_PW_Px`)
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PayloadLength {
+    /// The length is sent with every packet, in the 6-bit `LENGTH` field
+    Dynamic,
+    /// Every packet carries this many bytes. The 9-bit packet control field is the `S1` field
+    /// then, as the nRF24L01+ leaves its length bits unused
+    Static(u8),
+}
+
+impl PayloadLength {
+    /// Decodes the values of the `FEATURE` register and the `RX_PW_Px` register of the pipe
+    pub fn from_registers(feature: u8, rx_pw: u8) -> Self {
+        if feature & (1 << 2) != 0 {
+            Self::Dynamic
+        } else {
+            Self::Static(rx_pw & 0x3F)
+        }
+    }
+}
+
+/// The settings an nRF24L01+ and the nRF51 have to agree on. See the
+/// [module documentation](self)
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    /// The channel (`RF_CH`)
+    pub channel: u8,
+    /// The data rate
+    pub data_rate: DataRate,
+    /// The address width
+    pub address_width: AddressWidth,
+    /// The CRC length
+    pub crc: Crc,
+    /// How the length of the payload is determined
+    pub payload: PayloadLength,
+}
+
+impl Config {
+    /// The frequency of [`Self::channel`], or `None` if the nRF51 can't use it. The nRF24L01+
+    /// goes up to channel 125, the nRF51 only up to channel 100
+    pub fn frequency(&self) -> Option<Frequency> {
+        Frequency::from_mhz(FREQUENCY_OFFSET + u32::from(self.channel))
+    }
+
+    /// The packet layout
+    ///
+    /// Returns [`crate::Error::ValueOutOfBounds`] if a static payload length is `0` or longer
+    /// than [`MAX_PAYLOAD_LENGTH`].
+    pub fn packet_config(&self) -> crate::Result<PacketConfig> {
+        let balen = self.address_width.base_address_length();
+
+        match self.payload {
+            PayloadLength::Dynamic => Ok(PacketConfig {
+                balen,
+                ..Preset::EsbDynamic1M.packet_config()
+            }),
+            PayloadLength::Static(len @ 1..=MAX_PAYLOAD_LENGTH) => Ok(PacketConfig {
+                lf_len: LengthFieldLength(0),
+                s0_len: S0FieldLength(false),
+                s1_len: S1FieldLength(9),
+                maxlen: len,
+                statlen: len,
+                balen,
+                endian: Endianness::BIG,
+                whiten: false,
+            }),
+            PayloadLength::Static(_) => Err(crate::Error::ValueOutOfBounds),
+        }
+    }
+
+    /// The CRC configuration
+    pub fn crc_config(&self) -> CrcConfig {
+        self.crc.crc_config()
+    }
+}
+
+/// Converts an nRF24L01+ address, as written to `RX_ADDR_Px` or `TX_ADDR` (least significant
+/// byte first), to the physical address which goes over the air the same way
+///
+/// Returns [`crate::Error::ValueOutOfBounds`] if the address isn't 3 to 5 bytes long.
+pub fn physical_address(address: &[u8]) -> crate::Result<PhysicalAddress> {
+    let (&prefix, base) = address
+        .split_first()
+        .filter(|(_, base)| (2..=4).contains(&base.len()))
+        .ok_or(crate::Error::ValueOutOfBounds)?;
+
+    // the nRF51 sends the address least significant bit first, so the bits are mirrored
+    let base = base
+        .iter()
+        .rev()
+        .fold(0u32, |acc, &b| (acc << 8) | u32::from(b))
+        .reverse_bits()
+        >> (8 * (4 - base.len()));
+
+    Ok(PhysicalAddress {
+        base,
+        prefix: prefix.reverse_bits(),
+    })
+}
+
+impl<T, R: RadioRegisters> Radio<Enabled<T>, R> {
+    /// Set the mode, packet configuration, CRC configuration and frequency to the ones of an
+    /// nRF24L01+ configured like `config`
+    ///
+    /// Returns [`crate::Error::ValueOutOfBounds`] if the nRF51 can't use the channel, or if the
+    /// payload length is invalid.
+    pub fn apply_nrf24(&self, config: &Config) -> crate::Result<&Self> {
+        let frequency = config.frequency().ok_or(crate::Error::ValueOutOfBounds)?;
+
+        self.set_mode(config.data_rate.mode())
+            .set_packet_config(config.packet_config()?)?
+            .set_crc_config(config.crc_config())?
+            .set_frequency(frequency);
+
+        Ok(self)
+    }
+
+    /// Set the on-air address of a logical address to the nRF24L01+ address `nrf24`. See
+    /// [`physical_address`]
+    ///
+    /// Returns [`crate::Error::ValueOutOfBounds`] if the address doesn't have the width the
+    /// radio is set to.
+    pub fn set_nrf24_address(&self, address: Address, nrf24: &[u8]) -> crate::Result<&Self> {
+        let balen = self
+            .base_address_length()
+            .ok_or(crate::Error::ValueOutOfBounds)?;
+        if nrf24.len() != usize::from(balen.bytes()) + 1 {
+            return Err(crate::Error::ValueOutOfBounds);
+        }
+
+        self.set_physical_address(address, physical_address(nrf24)?)
+    }
+}
@@ -1,5 +1,7 @@
 //! Symbols related to sending and receiving packets
 
+use crate::{BaseAddressLength, Endianness};
+
 /// Maximum length of the `S0`, `LENGTH`, `S1`, and `PAYLOAD` fields combined
 pub const MAX_IN_MEMORY_PACKET_LENGTH: usize = 254;
 
@@ -9,6 +11,8 @@ pub const MAX_LENGTH_FIELD_BITS: u8 = 16;
 pub const MAX_S0_LENGTH_BYTES: u8 = 1;
 /// Maximum length of the `S1` field
 pub const MAX_S1_LENGTH_BITS: u8 = 16;
+/// Longest `LENGTH` and `S1` fields the 4-bit `LFLEN` and `S1LEN` fields of `PCNF0` can hold
+const MAX_PCNF0_FIELD_BITS: u8 = 15;
 
 /// The buffer that holds a packet
 pub type PacketBuffer = [u8; MAX_IN_MEMORY_PACKET_LENGTH];
@@ -23,6 +27,97 @@ pub struct Packet {
     lf_len: LengthFieldLength,
     s0_len: S0FieldLength,
     s1_len: S1FieldLength,
+    #[cfg_attr(feature = "defmt", defmt(Debug2Format))]
+    endian: Endianness,
+    statlen: u8,
+    maxlen: u8,
+}
+
+/// Packet configuration of the radio, covering the `PCNF0` and `PCNF1` registers
+///
+/// The configuration is validated as a whole and written with a single write per register, so
+/// that no field is changed by accident.
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PacketConfig {
+    /// Length of the `LENGTH` field
+    pub lf_len: LengthFieldLength,
+    /// Length of the `S0` field
+    pub s0_len: S0FieldLength,
+    /// Length of the `S1` field
+    pub s1_len: S1FieldLength,
+    /// Maximum length of the payload in bytes. Longer packets are truncated by the radio
+    pub maxlen: u8,
+    /// Length in bytes by which the payload is extended, on top of what the `LENGTH` field says
+    pub statlen: u8,
+    /// Length of the base address
+    pub balen: BaseAddressLength,
+    /// Order in which the bits of the `S0`, `LENGTH`, `S1`, and `PAYLOAD` fields go over the air.
+    /// In memory, `LENGTH` and `S1` are little endian either way
+    #[cfg_attr(feature = "defmt", defmt(Debug2Format))]
+    pub endian: Endianness,
+    /// Whether data whitening is enabled
+    pub whiten: bool,
+}
+
+impl PacketConfig {
+    /// Checks that the configuration makes sense, and that a received packet always fits into
+    /// [`PacketBuffer`]
+    ///
+    /// Returns [`crate::Error::ValueOutOfBounds`] if `S0`, `LENGTH`, `S1` and a payload of
+    /// [`Self::maxlen`] bytes don't fit into [`MAX_IN_MEMORY_PACKET_LENGTH`], if
+    /// [`Self::statlen`] is bigger than [`Self::maxlen`], or if the `LENGTH` or `S1` field is
+    /// longer than the 15 bits `PCNF0` can describe
+    pub fn validate(&self) -> crate::Result<()> {
+        if self.lf_len.bits() > MAX_PCNF0_FIELD_BITS || self.s1_len.bits() > MAX_PCNF0_FIELD_BITS {
+            return Err(crate::Error::ValueOutOfBounds);
+        }
+
+        if self.header_len() + usize::from(self.maxlen) > MAX_IN_MEMORY_PACKET_LENGTH {
+            return Err(crate::Error::ValueOutOfBounds);
+        }
+
+        if self.statlen > self.maxlen {
+            return Err(crate::Error::ValueOutOfBounds);
+        }
+
+        Ok(())
+    }
+
+    /// How many bytes the `S0`, `LENGTH` and `S1` fields occupy in memory
+    pub(crate) fn header_len(&self) -> usize {
+        self.s0_len.bytes() + self.lf_len.bytes() + self.s1_len.bytes()
+    }
+
+    /// How many bytes of `buf` the packet laid out at its start occupies, going by its `LENGTH`
+    /// field
+    #[cfg(feature = "mock")]
+    pub(crate) fn in_memory_len(&self, buf: &[u8]) -> usize {
+        let start = self.s0_len.bytes();
+        let end = (start + self.lf_len.bytes()).min(buf.len());
+        let length = read_field(&buf[start.min(end)..end]);
+
+        let payload = (usize::from(length) + usize::from(self.statlen)).min(self.maxlen.into());
+
+        (self.header_len() + payload).min(buf.len())
+    }
+}
+
+impl Default for PacketConfig {
+    /// An 8-bit `LENGTH` field, no `S0` or `S1`, the biggest payload that fits into
+    /// [`PacketBuffer`], a 4 byte base address, little endian and no whitening
+    fn default() -> Self {
+        Self {
+            lf_len: LengthFieldLength(8),
+            s0_len: S0FieldLength::default(),
+            s1_len: S1FieldLength::default(),
+            maxlen: (MAX_IN_MEMORY_PACKET_LENGTH - 1) as u8,
+            statlen: 0,
+            balen: BaseAddressLength(4),
+            endian: Endianness::LITTLE,
+            whiten: false,
+        }
+    }
 }
 
 /// See [MAX_LENGTH_FIELD_BITS]
@@ -49,6 +144,16 @@ impl LengthFieldLength {
 
         Some(Self(len))
     }
+
+    /// Get the length of the field in bits
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+
+    /// How many bytes the field occupies in memory
+    pub(crate) fn bytes(&self) -> usize {
+        usize::from(self.0.div_ceil(8))
+    }
 }
 
 impl S0FieldLength {
@@ -60,6 +165,11 @@ impl S0FieldLength {
 
         Some(Self(len != 0))
     }
+
+    /// Get the length of the field in bytes
+    pub fn bytes(&self) -> usize {
+        usize::from(self.0)
+    }
 }
 
 impl S1FieldLength {
@@ -71,9 +181,24 @@ impl S1FieldLength {
 
         Some(Self(len))
     }
+
+    /// Get the length of the field in bits
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+
+    /// How many bytes the field occupies in memory
+    pub(crate) fn bytes(&self) -> usize {
+        usize::from(self.0.div_ceil(8))
+    }
 }
 
 impl Packet {
+    /// Returns a builder with which a packet can be constructed
+    pub fn builder() -> PacketBuilder<'static> {
+        PacketBuilder::default()
+    }
+
     /// Constructs a new, zeroed out packet
     pub(crate) fn new_zeroed() -> Self {
         let buf: PacketBuffer = [0; _];
@@ -84,6 +209,9 @@ impl Packet {
             lf_len: LengthFieldLength::default(),
             s0_len: S0FieldLength::default(),
             s1_len: S1FieldLength::default(),
+            endian: Endianness::LITTLE,
+            statlen: 0,
+            maxlen: u8::MAX,
         }
     }
 
@@ -108,24 +236,476 @@ impl Packet {
         self
     }
 
-    /// Returns a pointer to the inner buffer. Use this as something you can pass to the radio
+    /// Set the byte order of the multi-byte fields
+    pub(crate) fn set_endianness(&mut self, endian: Endianness) -> &mut Self {
+        self.endian = endian;
+
+        self
+    }
+
+    /// Set the static length and the maximum length of the payload
+    pub(crate) fn set_payload_lens(&mut self, statlen: u8, maxlen: u8) -> &mut Self {
+        self.statlen = statlen;
+        self.maxlen = maxlen;
+
+        self
+    }
+
+    /// Get the length of the `LENGTH` field
+    pub fn lf_len(&self) -> LengthFieldLength {
+        self.lf_len
+    }
+
+    /// Get the length of the `S0` field
+    pub fn s0_len(&self) -> S0FieldLength {
+        self.s0_len
+    }
+
+    /// Get the length of the `S1` field
+    pub fn s1_len(&self) -> S1FieldLength {
+        self.s1_len
+    }
+
+    /// Get the endianness the packet was built with
+    pub fn endianness(&self) -> Endianness {
+        self.endian
+    }
+
+    /// Get the static length of the payload
+    pub fn statlen(&self) -> u8 {
+        self.statlen
+    }
+
+    pub(crate) fn buf_mut_ptr(&mut self) -> *mut u8 {
+        self.buffer.as_mut_ptr()
+    }
+
+    /// How many bytes the `S0`, `LENGTH` and `S1` fields occupy in memory
+    pub(crate) fn header_len(&self) -> usize {
+        self.s0_len.bytes() + self.lf_len.bytes() + self.s1_len.bytes()
+    }
+
+    /// How many bytes of the packet buffer are complete once the first `bits` bits following the
+    /// address have been received
+    ///
+    /// `LENGTH` and `S1` take fewer bits on the air than bytes in memory, so a field only counts
+    /// once all of its bits are in, and the payload only counts whole bytes.
+    pub(crate) fn received_len(&self, bits: u32) -> usize {
+        let fields = [
+            (u32::from(self.s0_len.0) * 8, self.s0_len.bytes()),
+            (u32::from(self.lf_len.bits()), self.lf_len.bytes()),
+            (u32::from(self.s1_len.bits()), self.s1_len.bytes()),
+        ];
+
+        let mut remaining = bits;
+        let mut len = 0;
+        for (field_bits, field_bytes) in fields {
+            if remaining < field_bits {
+                return len;
+            }
+
+            remaining -= field_bits;
+            len += field_bytes;
+        }
+
+        (len + remaining as usize / 8).min(MAX_IN_MEMORY_PACKET_LENGTH)
+    }
+
+    /// Copies `bytes` to the start of the packet buffer, so that the radio can transmit them
+    /// from there
+    pub(crate) fn load(&mut self, bytes: &[u8]) {
+        let len = bytes.len().min(MAX_IN_MEMORY_PACKET_LENGTH);
+        self.buffer[..len].copy_from_slice(&bytes[..len]);
+    }
+
+    /// Copies the first `len` bytes of the packet buffer at `src` into this packet, reading them
+    /// one by one, as the radio may be writing to it in the meantime
     ///
     /// # Safety
     ///
-    /// Make sure you send the packet away or utilize
-    // FIXME: incomplete docs
-    pub(crate) fn buf_ptr(&self) -> *const u8 {
-        self.buffer.as_ptr()
+    /// `src` MUST point to a [`PacketBuffer`]
+    pub(crate) unsafe fn copy_from_volatile(&mut self, src: *const u8, len: usize) {
+        for (i, b) in self.buffer.iter_mut().take(len).enumerate() {
+            // SAFETY: `i` is within the buffer, which the caller guarantees `src` points to
+            *b = unsafe { src.add(i).read_volatile() };
+        }
     }
 
-    pub(crate) fn buf_mut_ptr(&mut self) -> *mut u8 {
-        self.buffer.as_mut_ptr()
+    /// Get the value of the `S0` field, or `None` if the packet doesn't have one
+    pub fn s0(&self) -> Option<u8> {
+        self.s0_len.0.then(|| self.buffer[0])
+    }
+
+    /// Get the value of the `LENGTH` field, or `None` if the packet doesn't have one
+    pub fn length_field(&self) -> Option<u16> {
+        if self.lf_len.0 == 0 {
+            return None;
+        }
+
+        let start = self.s0_len.bytes();
+        let end = start + self.lf_len.bytes();
+
+        Some(read_field(&self.buffer[start..end]))
+    }
+
+    /// Get the value of the `S1` field, or `None` if the packet doesn't have one
+    pub fn s1(&self) -> Option<u16> {
+        if self.s1_len.0 == 0 {
+            return None;
+        }
+
+        let start = self.s0_len.bytes() + self.lf_len.bytes();
+        let end = start + self.s1_len.bytes();
+
+        Some(read_field(&self.buffer[start..end]))
+    }
+
+    /// How many bytes of payload the packet carries: the value of the `LENGTH` field plus the
+    /// static length, truncated to the maximum length
+    pub(crate) fn payload_len(&self) -> usize {
+        let len = usize::from(self.length_field().unwrap_or(0)) + usize::from(self.statlen);
+
+        len.min(usize::from(self.maxlen))
+    }
+
+    /// Get the payload of the packet
+    ///
+    /// If the `LENGTH` field of a received packet claims more data than fits into
+    /// [`PacketBuffer`], the payload is truncated.
+    pub fn payload(&self) -> &[u8] {
+        let start = self.header_len();
+        let end = (start + self.payload_len()).min(MAX_IN_MEMORY_PACKET_LENGTH);
+
+        &self.buffer[start..end]
     }
 
     /// Serialize this packet into a buffer that can be handed over to the radio
+    ///
+    /// Returns [`crate::Error::ValueOutOfBounds`] if the `LENGTH` field claims more data than fits
+    /// into [`PacketBuffer`]
     pub(crate) fn serialize(&self) -> crate::Result<SerializedPacketBuffer> {
-        todo!()
+        let len = self.header_len() + self.payload_len();
+        if len > MAX_IN_MEMORY_PACKET_LENGTH {
+            return Err(crate::Error::ValueOutOfBounds);
+        }
+
+        let mut buffer: PacketBuffer = [0; _];
+        buffer[0..len].copy_from_slice(&self.buffer[0..len]);
+
+        Ok(SerializedPacketBuffer { buffer, len })
+    }
+}
+
+/// Reads a field spanning `buf`. The radio stores fields little endian, whichever order their
+/// bits go over the air in
+fn read_field(buf: &[u8]) -> u16 {
+    buf.iter()
+        .rev()
+        .fold(0, |acc, b| (acc << 8) | u16::from(*b))
+}
+
+/// Writes `value` into a field spanning `buf`, little endian like [`read_field`]
+fn write_field(buf: &mut [u8], value: u16) {
+    for (i, b) in buf.iter_mut().enumerate() {
+        *b = (value >> (8 * i)) as u8;
+    }
+}
+
+/// Checks whether `value` can be represented with `bits` bits
+fn fits_in_bits(value: u16, bits: u8) -> bool {
+    bits >= 16 || value < (1 << bits)
+}
+
+/// Builds a [`Packet`]
+///
+/// The field lengths and endianness have to match the configuration of the radio the packet is
+/// going to be sent with. [`crate::Radio::packet_builder`] returns a builder which is already set
+/// up this way.
+#[derive(Debug, Clone)]
+pub struct PacketBuilder<'a> {
+    lf_len: LengthFieldLength,
+    s0_len: S0FieldLength,
+    s1_len: S1FieldLength,
+    endian: Endianness,
+    statlen: u8,
+
+    s0: Option<u8>,
+    s1: Option<u16>,
+    payload: &'a [u8],
+}
+
+impl Default for PacketBuilder<'_> {
+    fn default() -> Self {
+        Self {
+            lf_len: LengthFieldLength::default(),
+            s0_len: S0FieldLength::default(),
+            s1_len: S1FieldLength::default(),
+            endian: Endianness::LITTLE,
+            statlen: 0,
+
+            s0: None,
+            s1: None,
+            payload: &[],
+        }
+    }
+}
+
+impl<'a> PacketBuilder<'a> {
+    /// Set the length of the `LENGTH` field
+    pub fn lf_len(mut self, len: LengthFieldLength) -> Self {
+        self.lf_len = len;
+
+        self
+    }
+
+    /// Set the length of the `S0` field
+    pub fn s0_len(mut self, len: S0FieldLength) -> Self {
+        self.s0_len = len;
+
+        self
     }
+
+    /// Set the length of the `S1` field
+    pub fn s1_len(mut self, len: S1FieldLength) -> Self {
+        self.s1_len = len;
+
+        self
+    }
+
+    /// Set the byte order in which the multi-byte `LENGTH` and `S1` fields are laid out
+    pub fn endianness(mut self, endian: Endianness) -> Self {
+        self.endian = endian;
+
+        self
+    }
+
+    /// Set the static length of the payload. The `LENGTH` field only counts the bytes of the
+    /// payload past the static length
+    pub fn statlen(mut self, statlen: u8) -> Self {
+        self.statlen = statlen;
+
+        self
+    }
+
+    /// Take the field lengths, endianness and static length from a [`PacketConfig`]
+    pub fn config(self, config: &PacketConfig) -> Self {
+        self.lf_len(config.lf_len)
+            .s0_len(config.s0_len)
+            .s1_len(config.s1_len)
+            .endianness(config.endian)
+            .statlen(config.statlen)
+    }
+
+    /// Set the value of the `S0` field
+    pub fn s0(mut self, s0: u8) -> Self {
+        self.s0 = Some(s0);
+
+        self
+    }
+
+    /// Set the value of the `S1` field
+    pub fn s1(mut self, s1: u16) -> Self {
+        self.s1 = Some(s1);
+
+        self
+    }
+
+    /// Set the payload of the packet. The `LENGTH` field is derived from its length.
+    pub fn payload<'b>(self, payload: &'b [u8]) -> PacketBuilder<'b> {
+        PacketBuilder {
+            lf_len: self.lf_len,
+            s0_len: self.s0_len,
+            s1_len: self.s1_len,
+            endian: self.endian,
+            statlen: self.statlen,
+
+            s0: self.s0,
+            s1: self.s1,
+            payload,
+        }
+    }
+
+    /// Build the packet
+    ///
+    /// Returns [`crate::Error::ValueOutOfBounds`] if a value doesn't fit into its field (this
+    /// includes setting a field whose length is zero), if the payload is shorter than the static
+    /// length, or if the packet doesn't fit into [`MAX_IN_MEMORY_PACKET_LENGTH`].
+    pub fn build(&self) -> crate::Result<Packet> {
+        let mut p = Packet::new_zeroed();
+        p.set_lf_len(self.lf_len)
+            .set_s0_len(self.s0_len)
+            .set_s1_len(self.s1_len)
+            .set_endianness(self.endian)
+            .set_payload_lens(self.statlen, u8::MAX);
+
+        let header_len = p.header_len();
+        if header_len + self.payload.len() > MAX_IN_MEMORY_PACKET_LENGTH {
+            return Err(crate::Error::ValueOutOfBounds);
+        }
+
+        // the radio adds the static length to what the `LENGTH` field says
+        let length = self
+            .payload
+            .len()
+            .checked_sub(self.statlen.into())
+            .and_then(|l| u16::try_from(l).ok())
+            .ok_or(crate::Error::ValueOutOfBounds)?;
+        if !fits_in_bits(length, self.lf_len.0) {
+            return Err(crate::Error::ValueOutOfBounds);
+        }
+
+        let mut offset = 0;
+
+        if let Some(s0) = self.s0 {
+            if !self.s0_len.0 {
+                return Err(crate::Error::ValueOutOfBounds);
+            }
+
+            p.buffer[0] = s0;
+        }
+        offset += self.s0_len.bytes();
+
+        let lf_end = offset + self.lf_len.bytes();
+        write_field(&mut p.buffer[offset..lf_end], length);
+        offset = lf_end;
+
+        if let Some(s1) = self.s1 {
+            if self.s1_len.0 == 0 || !fits_in_bits(s1, self.s1_len.0) {
+                return Err(crate::Error::ValueOutOfBounds);
+            }
+
+            let s1_end = offset + self.s1_len.bytes();
+            write_field(&mut p.buffer[offset..s1_end], s1);
+        }
+        offset += self.s1_len.bytes();
+
+        p.buffer[offset..offset + self.payload.len()].copy_from_slice(self.payload);
+
+        Ok(p)
+    }
+}
+
+/// A packet received by the radio, along with information about its reception
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ReceivedPacket {
+    pub(crate) packet: Packet,
+    pub(crate) address: crate::Address,
+    pub(crate) crc: Option<u32>,
+    pub(crate) rssi: Option<i8>,
+    pub(crate) timestamp: Option<u64>,
+    pub(crate) device_address: Option<u8>,
+}
+
+impl ReceivedPacket {
+    /// Get the received packet
+    pub fn packet(&self) -> &Packet {
+        &self.packet
+    }
+
+    /// Take the received packet, discarding the reception information
+    pub fn into_packet(self) -> Packet {
+        self.packet
+    }
+
+    /// Get the logical address on which the packet was received (`RXMATCH`)
+    pub fn address(&self) -> crate::Address {
+        self.address
+    }
+
+    /// Get the CRC of the packet (`RXCRC`), or `None` if CRC is disabled
+    ///
+    /// Packets which fail the CRC check are never returned, so if this is `Some`, the CRC matched.
+    pub fn crc(&self) -> Option<u32> {
+        self.crc
+    }
+
+    /// Get the signal strength of the packet in dBm, sampled right after its address was
+    /// received. `None` if the sampling didn't finish before the end of the packet
+    pub fn rssi(&self) -> Option<i8> {
+        self.rssi
+    }
+
+    /// Get the time at which the address of the packet was received, as reported by the clock
+    /// passed to [`crate::Radio::receive_packet_with_timestamp`]. `None` if no clock was given
+    pub fn timestamp(&self) -> Option<u64> {
+        self.timestamp
+    }
+
+    /// Get the slot of the device address the packet matched (`DAI`), or `None` if it didn't
+    /// match any. See [`crate::Radio::set_device_address`]
+    pub fn device_address(&self) -> Option<u8> {
+        self.device_address
+    }
+}
+
+/// Maximum value of the data whitening initial value
+pub const MAX_WHITENING_IV: u8 = 0x7F;
+
+/// Software implementation of the data whitening done by the radio
+///
+/// The radio whitens the `S0`, `LENGTH`, `S1` and `PAYLOAD` fields and the CRC, in the order they
+/// are sent over the air, using the polynomial `x^7 + x^4 + 1`. Whitening is its own inverse, so
+/// the same operation is used to dewhiten received data.
+///
+/// The state is kept between calls to [`Self::apply`], so a frame can be processed in several
+/// pieces.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Whitener {
+    /// The shift register, laid out like the `DATAWHITEIV` register: bit 0 holds position 6 of
+    /// the LFSR, which is the one XOR'ed with the data
+    lfsr: u8,
+}
+
+impl Whitener {
+    /// Constructs a whitener initialized with `iv`, as it would be written into the
+    /// `DATAWHITEIV` register. Like in the hardware, bit 6 is always set
+    pub fn new(iv: u8) -> Self {
+        Self {
+            lfsr: (iv & MAX_WHITENING_IV) | 0x40,
+        }
+    }
+
+    /// Constructs a whitener the way Bluetooth Low Energy does it for `channel_index` (0-39)
+    pub fn for_ble_channel(channel_index: u8) -> Self {
+        Self::new(channel_index & 0x3F)
+    }
+
+    /// Whitens or dewhitens `data` in place
+    ///
+    /// `order` is the order in which the bits of each byte go over the air - the radio's
+    /// [`Endianness`] for the packet fields. The CRC is always sent most significant bit first.
+    pub fn apply(&mut self, data: &mut [u8], order: Endianness) {
+        for byte in data {
+            for i in 0..8 {
+                let bit = match order {
+                    Endianness::LITTLE => i,
+                    Endianness::BIG => 7 - i,
+                };
+
+                *byte ^= (self.lfsr & 1) << bit;
+                self.step();
+            }
+        }
+    }
+
+    /// Clocks the LFSR once
+    fn step(&mut self) {
+        let out = self.lfsr & 1;
+        self.lfsr >>= 1;
+
+        if out != 0 {
+            // feed the output back into position 0 and into the XOR in front of position 4
+            self.lfsr ^= 0x44;
+        }
+    }
+}
+
+/// Whitens or dewhitens `data` in place, as the radio would with `iv` in the `DATAWHITEIV`
+/// register. See [`Whitener`] for details
+pub fn whiten(data: &mut [u8], iv: u8, order: Endianness) {
+    Whitener::new(iv).apply(data, order);
 }
 
 /// A serialized packet
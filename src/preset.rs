@@ -0,0 +1,118 @@
+//! Named combinations of [`Mode`], packet layout and CRC that other radios expect
+//!
+//! Each on-air protocol only works with one packet layout and CRC for its data rate - for
+//! example a BLE packet sent with an ESB CRC is dropped by every BLE receiver, and nothing tells
+//! the sender. A [`Preset`] bundles everything that has to match, so that it can be set with
+//! [`Radio::apply_preset`] and checked with [`Radio::preset`].
+//!
+//! Presets don't cover what's chosen per link or per channel: the frequency, the addresses and
+//! the whitening IV (which BLE derives from the channel index) still have to be set separately.
+
+use strum::IntoEnumIterator;
+
+use crate::{
+    BaseAddressLength, CrcConfig, CrcLength, Enabled, Endianness, Mode, Radio, RadioRegisters,
+    packet::{LengthFieldLength, PacketConfig, S0FieldLength, S1FieldLength},
+};
+
+/// A radio configuration matching an on-air protocol
+#[derive(Copy, Clone, Eq, PartialEq, Debug, strum::EnumIter)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Preset {
+    /// Bluetooth Low Energy at 1 Mbit/s: an 8-bit header (`S0`), an 8-bit `LENGTH` field,
+    /// whitening and the 24-bit BLE CRC, initialised for advertising channels
+    ///
+    /// Connections use a different CRC initial value, which can be set with
+    /// [`Radio::set_crc_config`] afterwards.
+    Ble1M,
+    /// Enhanced ShockBurst with dynamic payload length at 250 kbit/s: a 6-bit `LENGTH` field, a
+    /// 3-bit `S1` holding the packet ID and the no-ACK flag, up to 32 bytes of payload and a
+    /// 16-bit CRC over the address
+    EsbDynamic250K,
+    /// Like [`Preset::EsbDynamic250K`], at 1 Mbit/s
+    EsbDynamic1M,
+    /// Like [`Preset::EsbDynamic250K`], at 2 Mbit/s
+    EsbDynamic2M,
+}
+
+impl Preset {
+    /// The data rate and modulation
+    pub fn mode(&self) -> Mode {
+        match self {
+            Self::Ble1M => Mode::BLE_1MBIT,
+            Self::EsbDynamic250K => Mode::NRF_250KBIT,
+            Self::EsbDynamic1M => Mode::NRF_1MBIT,
+            Self::EsbDynamic2M => Mode::NRF_2MBIT,
+        }
+    }
+
+    /// The packet layout, including whether whitening is enabled
+    pub fn packet_config(&self) -> PacketConfig {
+        match self {
+            Self::Ble1M => PacketConfig {
+                lf_len: LengthFieldLength(8),
+                s0_len: S0FieldLength(true),
+                s1_len: S1FieldLength(0),
+                maxlen: 251,
+                statlen: 0,
+                // the 4 byte access address is the prefix and a 3 byte base address
+                balen: BaseAddressLength(3),
+                endian: Endianness::LITTLE,
+                whiten: true,
+            },
+            Self::EsbDynamic250K | Self::EsbDynamic1M | Self::EsbDynamic2M => PacketConfig {
+                lf_len: LengthFieldLength(6),
+                s0_len: S0FieldLength(false),
+                s1_len: S1FieldLength(3),
+                maxlen: 32,
+                statlen: 0,
+                balen: BaseAddressLength(4),
+                endian: Endianness::BIG,
+                whiten: false,
+            },
+        }
+    }
+
+    /// The CRC configuration
+    pub fn crc_config(&self) -> CrcConfig {
+        match self {
+            Self::Ble1M => CrcConfig {
+                len: CrcLength::THREE,
+                skip_address: true,
+                // x^24 + x^10 + x^9 + x^6 + x^4 + x^3 + x + 1
+                polynomial: 0x00_065B,
+                init: 0x55_5555,
+            },
+            Self::EsbDynamic250K | Self::EsbDynamic1M | Self::EsbDynamic2M => CrcConfig {
+                len: CrcLength::TWO,
+                skip_address: false,
+                polynomial: 0x1021,
+                init: 0xFFFF,
+            },
+        }
+    }
+}
+
+impl<T, R: RadioRegisters> Radio<Enabled<T>, R> {
+    /// Set the mode, packet configuration (including whitening) and CRC configuration of
+    /// `preset`
+    pub fn apply_preset(&self, preset: Preset) -> crate::Result<&Self> {
+        self.set_mode(preset.mode())
+            .set_packet_config(preset.packet_config())?
+            .set_crc_config(preset.crc_config())
+    }
+
+    /// Whether the mode, packet configuration and CRC configuration the radio is set to are
+    /// exactly the ones of `preset`
+    pub fn matches_preset(&self, preset: Preset) -> bool {
+        self.mode() == preset.mode()
+            && self.packet_config() == Some(preset.packet_config())
+            && self.crc_config() == preset.crc_config()
+    }
+
+    /// Get the preset the radio is set to, or `None` if its configuration doesn't match any of
+    /// them
+    pub fn preset(&self) -> Option<Preset> {
+        Preset::iter().find(|&p| self.matches_preset(p))
+    }
+}
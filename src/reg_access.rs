@@ -1,128 +1,459 @@
-//! Raw register access functions, working directly on the PAC RADIO peripheral
-
-use nrf51_pac::RADIO;
+//! Raw register access functions, working on anything implementing [`RadioRegisters`]
 
 use crate::{
     BitMask,
-    packet::{LengthFieldLength, S0FieldLength, S1FieldLength},
+    packet::{LengthFieldLength, PacketConfig, S0FieldLength, S1FieldLength},
+    registers::{RadioRegisters, Register},
 };
 
-pub(crate) fn disable(radio: &RADIO) {
-    radio.tasks_disable.write(|w| unsafe { w.bits(1) });
+/// Triggers the task behind `reg`
+fn trigger<R: RadioRegisters>(radio: &R, reg: Register) {
+    radio.write(reg, 1);
+}
+
+/// Reads the `width` bits wide field starting at bit `shift` of `reg`
+fn read_field<R: RadioRegisters>(radio: &R, reg: Register, shift: u32, width: u32) -> u32 {
+    (radio.read(reg) >> shift) & ((1 << width) - 1)
+}
+
+/// Writes the `width` bits wide field starting at bit `shift` of `reg`, leaving the rest of the
+/// register untouched
+fn write_field<R: RadioRegisters>(radio: &R, reg: Register, shift: u32, width: u32, value: u32) {
+    radio.modify(reg, ((1 << width) - 1) << shift, value << shift);
+}
+
+pub(crate) fn disable<R: RadioRegisters>(radio: &R) {
+    trigger(radio, Register::TasksDisable);
+}
+
+pub(crate) fn enable_rx<R: RadioRegisters>(radio: &R) {
+    trigger(radio, Register::TasksRxEn);
+}
+
+pub(crate) fn enable_tx<R: RadioRegisters>(radio: &R) {
+    trigger(radio, Register::TasksTxEn);
 }
 
-pub(crate) fn enable_rx(radio: &RADIO) {
-    radio.tasks_rxen.write(|w| unsafe { w.bits(1) });
+pub(crate) fn read_frequency<R: RadioRegisters>(radio: &R) -> u32 {
+    radio.read(Register::Frequency)
 }
 
-pub(crate) fn enable_tx(radio: &RADIO) {
-    radio.tasks_txen.write(|w| unsafe { w.bits(1) });
+pub(crate) fn write_frequency<R: RadioRegisters>(radio: &R, freq: u32) {
+    radio.write(Register::Frequency, freq);
 }
 
-pub(crate) fn read_frequency(radio: &RADIO) -> u32 {
-    radio.frequency.read().bits()
+pub(crate) fn read_mode<R: RadioRegisters>(radio: &R) -> crate::Mode {
+    match read_field(radio, Register::Mode, 0, 2) {
+        0 => crate::Mode::NRF_1MBIT,
+        1 => crate::Mode::NRF_2MBIT,
+        2 => crate::Mode::NRF_250KBIT,
+        _ => crate::Mode::BLE_1MBIT,
+    }
 }
 
-pub(crate) fn write_frequency(radio: &RADIO, freq: u32) {
-    radio.frequency.write(|w| unsafe { w.bits(freq) });
+pub(crate) fn write_mode<R: RadioRegisters>(radio: &R, mode: crate::Mode) {
+    radio.write(Register::Mode, u8::from(mode).into());
 }
 
-pub(crate) fn read_mode(radio: &RADIO) -> crate::Mode {
-    radio.mode.read().mode().variant()
+pub(crate) fn get_endianness<R: RadioRegisters>(radio: &R) -> crate::Endianness {
+    match read_field(radio, Register::Pcnf1, 24, 1) {
+        0 => crate::Endianness::LITTLE,
+        _ => crate::Endianness::BIG,
+    }
 }
 
-pub(crate) fn write_mode(radio: &RADIO, mode: crate::Mode) {
-    radio.mode.write(|w| w.mode().variant(mode));
+pub(crate) fn set_endianness<R: RadioRegisters>(radio: &R, endian: crate::Endianness) {
+    write_field(radio, Register::Pcnf1, 24, 1, endian_bit(endian));
 }
 
-pub(crate) fn get_endianness(radio: &RADIO) -> crate::Endianness {
-    radio.pcnf1.read().endian().variant()
+fn endian_bit(endian: crate::Endianness) -> u32 {
+    match endian {
+        crate::Endianness::LITTLE => 0,
+        crate::Endianness::BIG => 1,
+    }
 }
 
-pub(crate) fn set_endianness(radio: &RADIO, endian: crate::Endianness) {
-    radio.pcnf1.write(|w| w.endian().variant(endian));
+pub(crate) fn read_interrupts<R: RadioRegisters>(radio: &R) -> crate::BitMask<u32> {
+    radio.read(Register::IntEnSet)
 }
 
-pub(crate) fn read_interrupts(radio: &RADIO) -> crate::BitMask<u32> {
-    radio.intenset.read().bits()
+pub(crate) fn enable_interrupt<R: RadioRegisters>(radio: &R, int: crate::Interrupt) {
+    radio.write(Register::IntEnSet, int as u32);
 }
 
-pub(crate) fn enable_interrupt(radio: &RADIO, int: crate::Interrupt) {
-    radio.intenset.write(|w| unsafe { w.bits(int as u32) });
+pub(crate) fn disable_interrupt<R: RadioRegisters>(radio: &R, int: crate::Interrupt) {
+    radio.write(Register::IntEnClr, int as u32);
 }
 
-pub(crate) fn disable_interrupt(radio: &RADIO, int: crate::Interrupt) {
-    radio.intenclr.write(|w| unsafe { w.bits(int as u32) });
+pub(crate) fn set_packet_ptr<R: RadioRegisters, T>(radio: &R, ptr: *mut T) {
+    radio.set_packet_ptr(ptr.cast());
 }
 
-pub(crate) fn set_packet_ptr<T>(radio: &RADIO, ptr: *mut T) {
-    radio.packetptr.write(|w| unsafe { w.bits(ptr as u32) });
+pub(crate) fn read_tx_power<R: RadioRegisters>(radio: &R) -> Option<crate::TxPower> {
+    use crate::TxPower;
+
+    let power = match read_field(radio, Register::TxPower, 0, 8) {
+        0x04 => TxPower::POS4D_BM,
+        0x00 => TxPower::_0D_BM,
+        0xFC => TxPower::NEG4D_BM,
+        0xF8 => TxPower::NEG8D_BM,
+        0xF4 => TxPower::NEG12D_BM,
+        0xF0 => TxPower::NEG16D_BM,
+        0xEC => TxPower::NEG20D_BM,
+        0xD8 => TxPower::NEG30D_BM,
+        _ => return None,
+    };
+
+    Some(power)
 }
 
-pub(crate) fn read_tx_power(radio: &RADIO) -> Option<crate::TxPower> {
-    radio.txpower.read().txpower().variant()
+pub(crate) fn write_tx_power<R: RadioRegisters>(radio: &R, tx_power: crate::TxPower) {
+    radio.write(Register::TxPower, u8::from(tx_power).into());
+}
+
+pub(crate) fn read_tx_address<R: RadioRegisters>(radio: &R) -> crate::BitMask<u8> {
+    read_field(radio, Register::TxAddress, 0, 3) as u8
+}
+
+pub(crate) fn write_tx_address<R: RadioRegisters>(radio: &R, addr: u32) {
+    radio.write(Register::TxAddress, addr);
+}
+
+pub(crate) fn read_rx_address<R: RadioRegisters>(radio: &R) -> BitMask<u8> {
+    read_field(radio, Register::RxAddresses, 0, 8) as u8
+}
+
+pub(crate) fn write_rx_address<R: RadioRegisters>(radio: &R, addr: u8) {
+    radio.write(Register::RxAddresses, addr.into());
+}
+
+pub(crate) fn read_balen<R: RadioRegisters>(radio: &R) -> u8 {
+    read_field(radio, Register::Pcnf1, 16, 3) as u8
+}
+
+pub(crate) fn write_balen<R: RadioRegisters>(radio: &R, len: u8) {
+    write_field(radio, Register::Pcnf1, 16, 3, len.into());
+}
+
+fn base_register(index: u8) -> Register {
+    match index {
+        0 => Register::Base0,
+        _ => Register::Base1,
+    }
 }
 
-pub(crate) fn write_tx_power(radio: &RADIO, tx_power: crate::TxPower) {
-    radio.txpower.write(|w| w.txpower().variant(tx_power));
+pub(crate) fn read_base<R: RadioRegisters>(radio: &R, index: u8) -> u32 {
+    radio.read(base_register(index))
 }
 
-pub(crate) fn read_tx_address(radio: &RADIO) -> crate::BitMask<u8> {
-    radio.txaddress.read().txaddress().bits()
+pub(crate) fn write_base<R: RadioRegisters>(radio: &R, index: u8, base: u32) {
+    radio.write(base_register(index), base);
 }
 
-pub(crate) fn write_tx_address(radio: &RADIO, addr: u32) {
-    radio.txaddress.write(|w| unsafe { w.bits(addr) });
+fn prefix_register(addr: u8) -> Register {
+    match addr {
+        0..=3 => Register::Prefix0,
+        _ => Register::Prefix1,
+    }
 }
 
-pub(crate) fn read_rx_address(radio: &RADIO) -> BitMask<u8> {
-    radio
-        .rxaddresses
-        .read()
-        .bits()
-        .try_into()
-        .expect("unable to fit a u32 into a u8") // this should never fail
+/// Reads the prefix of the logical address `addr`
+pub(crate) fn read_prefix<R: RadioRegisters>(radio: &R, addr: u8) -> u8 {
+    read_field(radio, prefix_register(addr), 8 * u32::from(addr % 4), 8) as u8
 }
 
-pub(crate) fn write_rx_address(radio: &RADIO, addr: u8) {
-    radio.txaddress.write(|w| unsafe { w.bits(addr.into()) });
+/// Writes the prefix of the logical address `addr`, leaving the other prefixes untouched
+pub(crate) fn write_prefix<R: RadioRegisters>(radio: &R, addr: u8, prefix: u8) {
+    write_field(
+        radio,
+        prefix_register(addr),
+        8 * u32::from(addr % 4),
+        8,
+        prefix.into(),
+    );
 }
 
-pub(crate) fn read_lf_len(radio: &RADIO) -> LengthFieldLength {
-    LengthFieldLength::from_bits(radio.pcnf0.read().lflen().bits())
+pub(crate) fn read_lf_len<R: RadioRegisters>(radio: &R) -> LengthFieldLength {
+    LengthFieldLength::from_bits(read_field(radio, Register::Pcnf0, 0, 4) as u8)
         .expect("invalid LENGTH field length in register")
 }
 
-pub(crate) fn write_lf_len(radio: &RADIO, len: LengthFieldLength) {
-    radio.pcnf0.write(|w| unsafe { w.lflen().bits(len.0) });
+pub(crate) fn write_lf_len<R: RadioRegisters>(radio: &R, len: LengthFieldLength) {
+    write_field(radio, Register::Pcnf0, 0, 4, len.0.into());
 }
 
-pub(crate) fn read_s0_len(radio: &RADIO) -> S0FieldLength {
-    S0FieldLength::from_bytes(radio.pcnf0.read().s0len().bit().into())
+pub(crate) fn read_s0_len<R: RadioRegisters>(radio: &R) -> S0FieldLength {
+    S0FieldLength::from_bytes(read_field(radio, Register::Pcnf0, 8, 1) as u8)
         .expect("invalid S0 field length in register")
 }
 
-pub(crate) fn write_s0_len(radio: &RADIO, len: S0FieldLength) {
-    radio.pcnf0.write(|w| w.s0len().bit(len.0));
+pub(crate) fn write_s0_len<R: RadioRegisters>(radio: &R, len: S0FieldLength) {
+    write_field(radio, Register::Pcnf0, 8, 1, len.0.into());
 }
 
-pub(crate) fn read_s1_len(radio: &RADIO) -> S1FieldLength {
-    S1FieldLength::from_bits(radio.pcnf0.read().s1len().bits())
+pub(crate) fn read_s1_len<R: RadioRegisters>(radio: &R) -> S1FieldLength {
+    S1FieldLength::from_bits(read_field(radio, Register::Pcnf0, 16, 4) as u8)
         .expect("invalid S1 field length in register")
 }
 
-pub(crate) fn write_s1_len(radio: &RADIO, len: S1FieldLength) {
-    radio.pcnf0.write(|w| unsafe { w.s1len().bits(len.0) });
+pub(crate) fn write_s1_len<R: RadioRegisters>(radio: &R, len: S1FieldLength) {
+    write_field(radio, Register::Pcnf0, 16, 4, len.0.into());
+}
+
+pub(crate) fn read_whitening<R: RadioRegisters>(radio: &R) -> bool {
+    read_field(radio, Register::Pcnf1, 25, 1) != 0
+}
+
+pub(crate) fn write_whitening<R: RadioRegisters>(radio: &R, enabled: bool) {
+    write_field(radio, Register::Pcnf1, 25, 1, enabled.into());
+}
+
+pub(crate) fn read_whitening_iv<R: RadioRegisters>(radio: &R) -> u8 {
+    read_field(radio, Register::DataWhiteIv, 0, 7) as u8
+}
+
+pub(crate) fn write_whitening_iv<R: RadioRegisters>(radio: &R, iv: u8) {
+    radio.write(Register::DataWhiteIv, iv.into());
+}
+
+pub(crate) fn read_crc_len<R: RadioRegisters>(radio: &R) -> crate::CrcLength {
+    match read_field(radio, Register::CrcCnf, 0, 2) {
+        0 => crate::CrcLength::DISABLED,
+        1 => crate::CrcLength::ONE,
+        2 => crate::CrcLength::TWO,
+        _ => crate::CrcLength::THREE,
+    }
+}
+
+pub(crate) fn read_crc_skip_address<R: RadioRegisters>(radio: &R) -> bool {
+    read_field(radio, Register::CrcCnf, 8, 1) != 0
+}
+
+pub(crate) fn write_crccnf<R: RadioRegisters>(
+    radio: &R,
+    len: crate::CrcLength,
+    skip_address: bool,
+) {
+    radio.write(
+        Register::CrcCnf,
+        u32::from(u8::from(len)) | (u32::from(skip_address) << 8),
+    );
 }
 
-pub(crate) fn get_state(radio: &RADIO) -> Option<crate::State> {
-    radio.state.read().state().variant()
+pub(crate) fn read_crc_poly<R: RadioRegisters>(radio: &R) -> u32 {
+    read_field(radio, Register::CrcPoly, 0, 24)
+}
+
+pub(crate) fn write_crc_poly<R: RadioRegisters>(radio: &R, poly: u32) {
+    radio.write(Register::CrcPoly, poly & crate::MAX_CRC_VALUE);
+}
+
+pub(crate) fn read_crc_init<R: RadioRegisters>(radio: &R) -> u32 {
+    read_field(radio, Register::CrcInit, 0, 24)
+}
+
+pub(crate) fn write_crc_init<R: RadioRegisters>(radio: &R, init: u32) {
+    radio.write(Register::CrcInit, init & crate::MAX_CRC_VALUE);
+}
+
+/// Whether the CRC of the last received packet was correct
+pub(crate) fn crc_ok<R: RadioRegisters>(radio: &R) -> bool {
+    read_field(radio, Register::CrcStatus, 0, 1) != 0
+}
+
+pub(crate) fn read_maxlen<R: RadioRegisters>(radio: &R) -> u8 {
+    read_field(radio, Register::Pcnf1, 0, 8) as u8
+}
+
+pub(crate) fn read_statlen<R: RadioRegisters>(radio: &R) -> u8 {
+    read_field(radio, Register::Pcnf1, 8, 8) as u8
+}
+
+pub(crate) fn write_packet_config<R: RadioRegisters>(radio: &R, config: &PacketConfig) {
+    radio.write(
+        Register::Pcnf0,
+        u32::from(config.lf_len.0)
+            | (u32::from(config.s0_len.0) << 8)
+            | (u32::from(config.s1_len.0) << 16),
+    );
+
+    radio.write(
+        Register::Pcnf1,
+        u32::from(config.maxlen)
+            | (u32::from(config.statlen) << 8)
+            | (u32::from(config.balen.0) << 16)
+            | (endian_bit(config.endian) << 24)
+            | (u32::from(config.whiten) << 25),
+    );
+}
+
+/// Reads `PCNF0` and `PCNF1`, without checking that the base address length is valid
+#[cfg(any(feature = "mock", feature = "sim"))]
+pub(crate) fn read_packet_config<R: RadioRegisters>(radio: &R) -> PacketConfig {
+    PacketConfig {
+        lf_len: read_lf_len(radio),
+        s0_len: read_s0_len(radio),
+        s1_len: read_s1_len(radio),
+        maxlen: read_maxlen(radio),
+        statlen: read_statlen(radio),
+        balen: crate::BaseAddressLength(read_balen(radio)),
+        endian: get_endianness(radio),
+        whiten: read_whitening(radio),
+    }
+}
+
+pub(crate) fn read_rx_crc<R: RadioRegisters>(radio: &R) -> u32 {
+    read_field(radio, Register::RxCrc, 0, 24)
+}
+
+pub(crate) fn read_rx_match<R: RadioRegisters>(radio: &R) -> u8 {
+    read_field(radio, Register::RxMatch, 0, 3) as u8
+}
+
+pub(crate) fn read_dai<R: RadioRegisters>(radio: &R) -> u8 {
+    read_field(radio, Register::Dai, 0, 3) as u8
+}
+
+pub(crate) fn read_dab<R: RadioRegisters>(radio: &R, slot: u8) -> u32 {
+    radio.read(Register::dab(slot))
+}
+
+pub(crate) fn write_dab<R: RadioRegisters>(radio: &R, slot: u8, base: u32) {
+    radio.write(Register::dab(slot), base);
+}
+
+pub(crate) fn read_dap<R: RadioRegisters>(radio: &R, slot: u8) -> u16 {
+    read_field(radio, Register::dap(slot), 0, 16) as u16
+}
+
+pub(crate) fn write_dap<R: RadioRegisters>(radio: &R, slot: u8, prefix: u16) {
+    radio.write(Register::dap(slot), prefix.into());
+}
+
+pub(crate) fn read_dacnf_ena<R: RadioRegisters>(radio: &R) -> BitMask<u8> {
+    read_field(radio, Register::DaCnf, 0, 8) as u8
+}
+
+pub(crate) fn write_dacnf_ena<R: RadioRegisters>(radio: &R, enabled: BitMask<u8>) {
+    write_field(radio, Register::DaCnf, 0, 8, enabled.into());
+}
+
+pub(crate) fn read_dacnf_txadd<R: RadioRegisters>(radio: &R, slot: u8) -> bool {
+    read_field(radio, Register::DaCnf, 8 + u32::from(slot), 1) != 0
+}
+
+pub(crate) fn write_dacnf_txadd<R: RadioRegisters>(radio: &R, slot: u8, tx_add: bool) {
+    write_field(
+        radio,
+        Register::DaCnf,
+        8 + u32::from(slot),
+        1,
+        tx_add.into(),
+    );
+}
+
+pub(crate) fn read_tifs<R: RadioRegisters>(radio: &R) -> u8 {
+    read_field(radio, Register::Tifs, 0, 8) as u8
+}
+
+pub(crate) fn write_tifs<R: RadioRegisters>(radio: &R, us: u8) {
+    write_field(radio, Register::Tifs, 0, 8, us.into());
+}
+
+pub(crate) fn read_bcc<R: RadioRegisters>(radio: &R) -> u32 {
+    radio.read(Register::Bcc)
+}
+
+pub(crate) fn write_bcc<R: RadioRegisters>(radio: &R, bits: u32) {
+    radio.write(Register::Bcc, bits);
+}
+
+pub(crate) fn read_rssi_sample<R: RadioRegisters>(radio: &R) -> u8 {
+    read_field(radio, Register::RssiSample, 0, 7) as u8
+}
+
+pub(crate) fn read_shortcuts<R: RadioRegisters>(radio: &R) -> u32 {
+    radio.read(Register::Shorts)
+}
+
+pub(crate) fn write_shortcuts<R: RadioRegisters>(radio: &R, shortcuts: u32) {
+    radio.write(Register::Shorts, shortcuts);
+}
+
+pub(crate) fn get_state<R: RadioRegisters>(radio: &R) -> Option<crate::State> {
+    use crate::State;
+
+    let state = match read_field(radio, Register::State, 0, 4) {
+        0 => State::DISABLED,
+        1 => State::RX_RU,
+        2 => State::RX_IDLE,
+        3 => State::RX,
+        4 => State::RX_DISABLE,
+        9 => State::TX_RU,
+        10 => State::TX_IDLE,
+        11 => State::TX,
+        12 => State::TX_DISABLE,
+        _ => return None,
+    };
+
+    Some(state)
 }
 
 pub(crate) mod tasks {
-    use super::RADIO;
+    use super::trigger;
+    use crate::registers::{RadioRegisters, Register};
+
+    pub(crate) fn start<R: RadioRegisters>(radio: &R) {
+        trigger(radio, Register::TasksStart);
+    }
+
+    pub(crate) fn stop<R: RadioRegisters>(radio: &R) {
+        trigger(radio, Register::TasksStop);
+    }
+
+    pub(crate) fn rssistart<R: RadioRegisters>(radio: &R) {
+        trigger(radio, Register::TasksRssiStart);
+    }
+
+    pub(crate) fn rssistop<R: RadioRegisters>(radio: &R) {
+        trigger(radio, Register::TasksRssiStop);
+    }
+
+    pub(crate) fn bcstart<R: RadioRegisters>(radio: &R) {
+        trigger(radio, Register::TasksBcStart);
+    }
+
+    pub(crate) fn bcstop<R: RadioRegisters>(radio: &R) {
+        trigger(radio, Register::TasksBcStop);
+    }
+}
+
+pub(crate) mod events {
+    use crate::{
+        Event,
+        registers::{RadioRegisters, Register},
+    };
+
+    /// The register which `event` is raised in
+    pub(crate) fn register(event: Event) -> Register {
+        match event {
+            Event::Ready => Register::EventsReady,
+            Event::Address => Register::EventsAddress,
+            Event::Payload => Register::EventsPayload,
+            Event::End => Register::EventsEnd,
+            Event::Disabled => Register::EventsDisabled,
+            Event::DevMatch => Register::EventsDevMatch,
+            Event::DevMiss => Register::EventsDevMiss,
+            Event::RSSIEnd => Register::EventsRssiEnd,
+            Event::BCMatch => Register::EventsBcMatch,
+        }
+    }
+
+    pub(crate) fn occurred<R: RadioRegisters>(radio: &R, event: Event) -> bool {
+        radio.read(register(event)) != 0
+    }
 
-    pub(crate) fn start(radio: &RADIO) {
-        radio.tasks_start.write(|w| unsafe { w.bits(1) });
+    pub(crate) fn clear<R: RadioRegisters>(radio: &R, event: Event) {
+        radio.write(register(event), 0);
     }
 }
@@ -0,0 +1,182 @@
+//! Access to the RADIO registers, abstracted so that the driver can run against something other
+//! than the real peripheral
+
+/// The RADIO registers used by this crate, with their offsets in the register block as the
+/// discriminant
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u16)]
+#[allow(missing_docs)]
+pub enum Register {
+    TasksTxEn = 0x000,
+    TasksRxEn = 0x004,
+    TasksStart = 0x008,
+    TasksStop = 0x00C,
+    TasksDisable = 0x010,
+    TasksRssiStart = 0x014,
+    TasksRssiStop = 0x018,
+    TasksBcStart = 0x01C,
+    TasksBcStop = 0x020,
+    EventsReady = 0x100,
+    EventsAddress = 0x104,
+    EventsPayload = 0x108,
+    EventsEnd = 0x10C,
+    EventsDisabled = 0x110,
+    EventsDevMatch = 0x114,
+    EventsDevMiss = 0x118,
+    EventsRssiEnd = 0x11C,
+    EventsBcMatch = 0x128,
+    Shorts = 0x200,
+    IntEnSet = 0x304,
+    IntEnClr = 0x308,
+    CrcStatus = 0x400,
+    RxMatch = 0x408,
+    RxCrc = 0x40C,
+    Dai = 0x410,
+    PacketPtr = 0x504,
+    Frequency = 0x508,
+    TxPower = 0x50C,
+    Mode = 0x510,
+    Pcnf0 = 0x514,
+    Pcnf1 = 0x518,
+    Base0 = 0x51C,
+    Base1 = 0x520,
+    Prefix0 = 0x524,
+    Prefix1 = 0x528,
+    TxAddress = 0x52C,
+    RxAddresses = 0x530,
+    CrcCnf = 0x534,
+    CrcPoly = 0x538,
+    CrcInit = 0x53C,
+    Tifs = 0x544,
+    RssiSample = 0x548,
+    State = 0x550,
+    DataWhiteIv = 0x554,
+    Bcc = 0x560,
+    Dab0 = 0x600,
+    Dab1 = 0x604,
+    Dab2 = 0x608,
+    Dab3 = 0x60C,
+    Dab4 = 0x610,
+    Dab5 = 0x614,
+    Dab6 = 0x618,
+    Dab7 = 0x61C,
+    Dap0 = 0x620,
+    Dap1 = 0x624,
+    Dap2 = 0x628,
+    Dap3 = 0x62C,
+    Dap4 = 0x630,
+    Dap5 = 0x634,
+    Dap6 = 0x638,
+    Dap7 = 0x63C,
+    DaCnf = 0x640,
+}
+
+impl Register {
+    /// The offset of the register from the start of the register block, in bytes
+    pub const fn offset(self) -> usize {
+        self as usize
+    }
+
+    /// The `DAB` register of device address `slot`
+    pub(crate) fn dab(slot: u8) -> Self {
+        const DAB: [Register; 8] = [
+            Register::Dab0,
+            Register::Dab1,
+            Register::Dab2,
+            Register::Dab3,
+            Register::Dab4,
+            Register::Dab5,
+            Register::Dab6,
+            Register::Dab7,
+        ];
+
+        DAB[usize::from(slot % 8)]
+    }
+
+    /// The `DAP` register of device address `slot`
+    pub(crate) fn dap(slot: u8) -> Self {
+        const DAP: [Register; 8] = [
+            Register::Dap0,
+            Register::Dap1,
+            Register::Dap2,
+            Register::Dap3,
+            Register::Dap4,
+            Register::Dap5,
+            Register::Dap6,
+            Register::Dap7,
+        ];
+
+        DAP[usize::from(slot % 8)]
+    }
+}
+
+/// Something that behaves like the RADIO peripheral's registers
+///
+/// This is implemented for [`nrf51_pac::RADIO`], which is what [`crate::Radio`] uses by default.
+/// Other implementations (like the `mock` feature's [`crate::mock::MockRadio`]) can be used to
+/// run the driver on the host.
+///
+/// Reads and writes work on whole registers, with the same semantics as the hardware: writing 1
+/// to a task register triggers the task, `INTENSET`/`INTENCLR` only set/clear the bits written
+/// as 1, and so on.
+pub trait RadioRegisters {
+    /// Reads the value of `reg`
+    fn read(&self, reg: Register) -> u32;
+
+    /// Writes `value` to `reg`
+    fn write(&self, reg: Register, value: u32);
+
+    /// Points `PACKETPTR` to `ptr`
+    ///
+    /// The hardware register is only 32 bits wide, so implementations which don't run on the
+    /// nRF51 itself will want to override this to keep the whole pointer.
+    fn set_packet_ptr(&self, ptr: *mut u8) {
+        self.write(Register::PacketPtr, ptr as u32);
+    }
+
+    /// Reads `reg`, replaces the bits in `mask` with `value` and writes it back
+    fn modify(&self, reg: Register, mask: u32, value: u32) {
+        let r = self.read(reg);
+        self.write(reg, (r & !mask) | (value & mask));
+    }
+}
+
+impl RadioRegisters for nrf51_pac::RADIO {
+    fn read(&self, reg: Register) -> u32 {
+        // SAFETY: the offset of `reg` lies within the register block, and is word-aligned
+        unsafe {
+            Self::PTR
+                .cast::<u8>()
+                .add(reg.offset())
+                .cast::<u32>()
+                .read_volatile()
+        }
+    }
+
+    fn write(&self, reg: Register, value: u32) {
+        // SAFETY: the offset of `reg` lies within the register block, and is word-aligned
+        unsafe {
+            Self::PTR
+                .cast::<u8>()
+                .add(reg.offset())
+                .cast::<u32>()
+                .cast_mut()
+                .write_volatile(value)
+        }
+    }
+}
+
+impl<R: RadioRegisters> RadioRegisters for &R {
+    fn read(&self, reg: Register) -> u32 {
+        (**self).read(reg)
+    }
+
+    fn write(&self, reg: Register, value: u32) {
+        (**self).write(reg, value);
+    }
+
+    fn set_packet_ptr(&self, ptr: *mut u8) {
+        (**self).set_packet_ptr(ptr);
+    }
+}
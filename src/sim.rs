@@ -0,0 +1,814 @@
+//! A behavioural simulator of the RADIO peripheral, connected to other simulated radios through
+//! a shared [`Air`]
+//!
+//! Unlike [`crate::mock`], the simulator models what goes over the air: a transmitted packet is
+//! read from `PACKETPTR` and sent bit by bit, each field as long as the transmitter's
+//! configuration says and in the bit order `ENDIAN` gives, followed by its CRC and whitened if
+//! whitening is enabled. Every other radio that is in [`State::RX`] on the same
+//! [`crate::Frequency`] and in the same [`crate::Mode`] then matches the address against the ones
+//! it listens to, dewhitens the bits, decodes the fields with its own configuration, checks the
+//! CRC and writes the packet to its own `PACKETPTR`, raising the same events the hardware would.
+//!
+//! Ramp-up takes no time, and a packet reaches the receivers as soon as `START` is triggered,
+//! raising `ADDRESS` on both ends. The rest of the packet is in flight until the driver has looked
+//! for its end twice - by reading `STATE`, the CRC status or any event that is raised after the
+//! address - so a driver which reacts to `ADDRESS` between looking for `END` is always fast enough.
+//! [`SimRadio::set_airtime`] changes how many looks that takes, down to none, where the packet ends
+//! at the first register the driver reads, as if the driver were too slow to see anything but its
+//! end. A look which doesn't end a packet being received lets it get as far as the bit counter
+//! compares to, writing the bytes completed by then and raising `BCMATCH`. Once the packet ends,
+//! shortcuts are followed, so a receiver which turns around with `END_DISABLE` and `DISABLED_TXEN`
+//! answers right away. As the driver's blocking functions spin until something happens, the
+//! simulated radios are meant to be driven from separate threads:
+//!
+//! ```ignore
+//! let air = Air::new();
+//! let (a, b) = (air.radio(), air.radio());
+//!
+//! let rx = std::thread::spawn(move || {
+//!     let radio = Radio::new_zeroed(&b).into_receiver();
+//!     // ...
+//!     radio.receive_packet()
+//! });
+//!
+//! let radio = Radio::new_zeroed(&a).into_transmitter();
+//! // ...
+//! radio.transmit_packet(&packet)?;
+//! ```
+
+extern crate std;
+
+use core::cell::{Cell, RefCell};
+use std::{
+    sync::{Arc, Mutex, MutexGuard, PoisonError, Weak},
+    vec::Vec,
+};
+
+use crate::{
+    Address, CrcLength, Endianness, Frequency, Shortcuts, State,
+    packet::{MAX_IN_MEMORY_PACKET_LENGTH, PacketConfig, Whitener},
+    reg_access,
+    registers::{RadioRegisters, Register},
+};
+
+/// Number of words in the RADIO register block
+const REGISTER_COUNT: usize = 0x1000 / 4;
+
+/// How many times the driver has to look for the end of a packet before it ends, unless
+/// [`SimRadio::set_airtime`] says otherwise
+const DEFAULT_AIRTIME: u8 = 2;
+
+/// The medium simulated radios transmit into
+///
+/// Every [`SimRadio`] constructed with [`Air::radio`] hears the others.
+#[derive(Default)]
+pub struct Air {
+    nodes: Mutex<Vec<Weak<Mutex<Node>>>>,
+}
+
+impl Air {
+    /// Constructs an empty medium
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Constructs a radio with all registers set to zero, connected to this medium
+    pub fn radio(self: &Arc<Self>) -> SimRadio {
+        let node = Arc::new(Mutex::new(Node::new()));
+
+        let mut nodes = lock(&self.nodes);
+        nodes.retain(|n| n.strong_count() > 0);
+        nodes.push(Arc::downgrade(&node));
+
+        SimRadio {
+            node,
+            air: Arc::clone(self),
+        }
+    }
+
+    /// Delivers `transmission` to every radio except `from`
+    fn broadcast(&self, from: &Arc<Mutex<Node>>, transmission: &Transmission) {
+        let nodes: Vec<_> = lock(&self.nodes)
+            .iter()
+            .filter_map(Weak::upgrade)
+            .filter(|n| !Arc::ptr_eq(n, from))
+            .collect();
+
+        for node in nodes {
+            lock(&node).deliver(transmission);
+        }
+    }
+}
+
+/// A simulated RADIO peripheral, connected to an [`Air`]. See the
+/// [module documentation](self)
+pub struct SimRadio {
+    node: Arc<Mutex<Node>>,
+    air: Arc<Air>,
+}
+
+impl SimRadio {
+    /// The current state of the radio
+    pub fn state(&self) -> Option<State> {
+        reg_access::get_state(&*lock(&self.node))
+    }
+
+    /// The frequency the radio is tuned to, or `None` if `FREQUENCY` doesn't hold a valid one
+    pub fn frequency(&self) -> Option<Frequency> {
+        Frequency::from_reg_value(reg_access::read_frequency(&*lock(&self.node)))
+    }
+
+    /// Sets the signal strength the radio samples, in -dBm
+    pub fn set_rssi(&self, rssi: u8) {
+        lock(&self.node).rssi = rssi & 0x7F;
+    }
+
+    /// Sets how many times the driver has to look for the end of a packet this radio sends or
+    /// receives before it ends. With `0`, the packet ends at the first register read after it
+    /// started
+    pub fn set_airtime(&self, looks: u8) {
+        lock(&self.node).airtime = looks;
+    }
+
+    /// Puts `transmission` on the air
+    fn run(&self, transmission: Option<Transmission>) {
+        if let Some(transmission) = transmission {
+            self.air.broadcast(&self.node, &transmission);
+        }
+    }
+}
+
+/// Whether reading `reg` shows if the packet in flight has ended
+fn ends_packet(reg: Register) -> bool {
+    matches!(
+        reg,
+        Register::EventsPayload
+            | Register::EventsEnd
+            | Register::EventsDisabled
+            | Register::EventsDevMatch
+            | Register::EventsDevMiss
+            | Register::EventsBcMatch
+            | Register::CrcStatus
+            | Register::RxCrc
+            | Register::Dai
+            | Register::State
+    )
+}
+
+impl RadioRegisters for SimRadio {
+    fn read(&self, reg: Register) -> u32 {
+        let (value, transmission) = {
+            let node = lock(&self.node);
+            let transmission = if ends_packet(reg) || node.airtime == 0 {
+                node.look()
+            } else {
+                None
+            };
+
+            (node.read(reg), transmission)
+        };
+
+        self.run(transmission);
+
+        value
+    }
+
+    fn write(&self, reg: Register, value: u32) {
+        let transmission = {
+            let node = lock(&self.node);
+
+            match reg {
+                Register::TasksTxEn => node.enable(State::TX_RU, State::TX_IDLE),
+                Register::TasksRxEn => node.enable(State::RX_RU, State::RX_IDLE),
+                Register::TasksStart => node.start(),
+                Register::TasksDisable => node.disable(),
+                Register::TasksStop => {
+                    node.stop();
+                    None
+                }
+                Register::TasksRssiStart => {
+                    node.sample_rssi();
+                    None
+                }
+                Register::IntEnSet => {
+                    node.set(reg, node.get(reg) | value);
+                    None
+                }
+                Register::IntEnClr => {
+                    node.set(Register::IntEnSet, node.get(Register::IntEnSet) & !value);
+                    None
+                }
+                Register::TasksBcStart => {
+                    node.bit_counter.set(true);
+                    None
+                }
+                Register::TasksBcStop => {
+                    node.bit_counter.set(false);
+                    None
+                }
+                Register::TasksRssiStop
+                | Register::CrcStatus
+                | Register::RxMatch
+                | Register::RxCrc
+                | Register::Dai
+                | Register::RssiSample
+                | Register::State => None,
+                _ => {
+                    node.set(reg, value);
+                    None
+                }
+            }
+        };
+
+        self.run(transmission);
+    }
+
+    fn set_packet_ptr(&self, ptr: *mut u8) {
+        lock(&self.node).packet_ptr.set(ptr as usize);
+    }
+}
+
+/// The on-air address: the prefix followed by the base address
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+struct AirAddress {
+    prefix: u8,
+    base: u32,
+    balen: u8,
+}
+
+impl AirAddress {
+    /// The bytes of the address, in the order they go over the air
+    fn bytes(&self) -> impl Iterator<Item = u8> {
+        let base = self.base;
+
+        core::iter::once(self.prefix).chain((0..self.balen).map(move |i| (base >> (8 * i)) as u8))
+    }
+}
+
+/// Bits in the order they go over the air
+#[derive(Clone, Debug, Default)]
+struct Bits(Vec<bool>);
+
+impl Bits {
+    /// Appends the lowest `len` bits of `value`, sent in `order`
+    fn push(&mut self, value: u32, len: usize, order: Endianness) {
+        for i in 0..len {
+            self.0.push((value >> bit_index(i, len, order)) & 1 != 0);
+        }
+    }
+
+    /// Reads the `len` bits at `pos` as a value sent in `order`, moving `pos` past them. Bits
+    /// past the end read as zero
+    fn take(&self, pos: &mut usize, len: usize, order: Endianness) -> u32 {
+        let value = (0..len)
+            .filter(|&i| self.0.get(*pos + i) == Some(&true))
+            .fold(0, |acc, i| acc | (1 << bit_index(i, len, order)));
+        *pos += len;
+
+        value
+    }
+}
+
+/// Which bit of a `len` bit value goes over the air as the `i`th one, in `order`
+fn bit_index(i: usize, len: usize, order: Endianness) -> usize {
+    match order {
+        Endianness::LITTLE => i,
+        Endianness::BIG => len - 1 - i,
+    }
+}
+
+/// Reads a field of the packet in memory, where the radio keeps it little endian
+fn read_field(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0, |acc, b| (acc << 8) | u32::from(*b))
+}
+
+/// A packet on the air
+#[derive(Clone, Debug)]
+struct Transmission {
+    frequency: u32,
+    mode: u32,
+    address: AirAddress,
+    /// `S0`, `LENGTH`, `S1`, the payload and the CRC, whitened if the transmitter had whitening
+    /// enabled
+    bits: Bits,
+}
+
+/// A packet as a receiver decodes it
+struct Decoded {
+    /// The packet laid out the way the radio writes it to memory
+    memory: Vec<u8>,
+    /// For each byte of `memory`, how many bits after the address have to arrive before it's
+    /// complete
+    complete_at: Vec<usize>,
+    /// The CRC which followed the payload
+    crc: u32,
+    /// Whether the CRC matches, and the transmission was as long as the fields said
+    crc_ok: bool,
+}
+
+/// A packet which has started, but hasn't ended yet
+enum InFlight {
+    Tx,
+    /// `transmission` is being received into the buffer at `ptr`
+    Rx {
+        transmission: Transmission,
+        ptr: usize,
+    },
+}
+
+/// The state of a simulated radio
+struct Node {
+    registers: [Cell<u32>; REGISTER_COUNT],
+    packet_ptr: Cell<usize>,
+    in_flight: RefCell<Option<InFlight>>,
+    /// How many times the driver has looked for the end of the packet in flight
+    looks: Cell<u8>,
+    /// How many looks a packet takes
+    airtime: u8,
+    rssi: u8,
+    /// Whether the bit counter is running
+    bit_counter: Cell<bool>,
+}
+
+impl Node {
+    fn new() -> Self {
+        Self {
+            registers: [const { Cell::new(0) }; REGISTER_COUNT],
+            packet_ptr: Cell::new(0),
+            in_flight: RefCell::new(None),
+            looks: Cell::new(0),
+            airtime: DEFAULT_AIRTIME,
+            rssi: 0,
+            bit_counter: Cell::new(false),
+        }
+    }
+
+    fn get(&self, reg: Register) -> u32 {
+        self.registers[reg.offset() / 4].get()
+    }
+
+    fn set(&self, reg: Register, value: u32) {
+        self.registers[reg.offset() / 4].set(value);
+    }
+
+    fn state(&self) -> Option<State> {
+        reg_access::get_state(self)
+    }
+
+    fn set_state(&self, state: State) {
+        self.set(Register::State, u8::from(state).into());
+    }
+
+    fn raise(&self, event: Register) {
+        self.set(event, 1);
+    }
+
+    fn shortcuts(&self) -> Shortcuts {
+        Shortcuts::from_bits_truncate(self.get(Register::Shorts))
+    }
+
+    /// The packet buffer `PACKETPTR` points to
+    ///
+    /// # Safety
+    ///
+    /// The driver has to keep the buffer alive for as long as the radio may access it, which it
+    /// does while a packet is in flight
+    unsafe fn packet_buffer(&self) -> &[u8] {
+        let ptr = self.packet_ptr.get() as *const u8;
+
+        // SAFETY: guaranteed by the caller
+        unsafe { core::slice::from_raw_parts(ptr, MAX_IN_MEMORY_PACKET_LENGTH) }
+    }
+
+    /// The on-air address of the logical address `address`
+    fn air_address(&self, address: u8) -> AirAddress {
+        let balen = reg_access::read_balen(self).min(4);
+        let index = Address::from_repr(address).map_or(1, Address::base_index);
+
+        AirAddress {
+            prefix: reg_access::read_prefix(self, address),
+            base: reg_access::read_base(self, index) >> (8 * u32::from(4 - balen)),
+            balen,
+        }
+    }
+
+    /// Calculates the CRC of a packet, the way the radio's CRC generator is configured
+    fn crc(&self, address: &AirAddress, pdu: &[bool]) -> u32 {
+        let len = u32::from(u8::from(reg_access::read_crc_len(self)));
+        if len == 0 {
+            return 0;
+        }
+
+        let poly = reg_access::read_crc_poly(self);
+        let mut crc = reg_access::read_crc_init(self);
+
+        let bits = 8 * len;
+        let mask = (1u32 << bits) - 1;
+        let top = 1 << (bits - 1);
+
+        let mut feed = |bit: bool| {
+            let feedback = bit != (crc & top != 0);
+            crc = (crc << 1) & mask;
+            if feedback {
+                crc ^= poly & mask;
+            }
+        };
+
+        if !reg_access::read_crc_skip_address(self) {
+            // the address goes out least significant bit first
+            for byte in address.bytes() {
+                (0..8).for_each(|i| feed((byte >> i) & 1 != 0));
+            }
+        }
+        pdu.iter().for_each(|bit| feed(*bit));
+
+        crc
+    }
+
+    /// Whitens (or dewhitens) `bits` - the PDU followed by the CRC
+    fn whiten(&self, bits: &mut Bits, config: &PacketConfig) {
+        if !config.whiten {
+            return;
+        }
+
+        // the whitener works on bytes, so the bits are packed into them in the order they're sent
+        let mut bytes = std::vec![0; bits.0.len().div_ceil(8)];
+        for (i, bit) in bits.0.iter().enumerate() {
+            bytes[i / 8] |= u8::from(*bit) << (i % 8);
+        }
+
+        Whitener::new(reg_access::read_whitening_iv(self)).apply(&mut bytes, Endianness::LITTLE);
+
+        for (i, bit) in bits.0.iter_mut().enumerate() {
+            *bit = (bytes[i / 8] >> (i % 8)) & 1 != 0;
+        }
+    }
+
+    fn crc_len(&self) -> usize {
+        u8::from(reg_access::read_crc_len(self)).into()
+    }
+
+    fn enable(&self, ramp_up: State, idle: State) -> Option<Transmission> {
+        if self.state() != Some(State::DISABLED) {
+            return None;
+        }
+
+        self.set_state(ramp_up);
+        self.set_state(idle);
+        self.raise(Register::EventsReady);
+
+        if self.shortcuts().contains(Shortcuts::READY_START) {
+            return self.start();
+        }
+
+        None
+    }
+
+    fn start(&self) -> Option<Transmission> {
+        match self.state() {
+            Some(State::TX_IDLE) => {
+                self.set_state(State::TX);
+                let transmission = self.outgoing();
+
+                self.address();
+                self.fly(InFlight::Tx);
+
+                Some(transmission)
+            }
+            Some(State::RX_IDLE) => {
+                self.set_state(State::RX);
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn stop(&self) {
+        // the packet in flight is cut short
+        self.in_flight.borrow_mut().take();
+
+        match self.state() {
+            Some(State::RX) => self.set_state(State::RX_IDLE),
+            Some(State::TX) => self.set_state(State::TX_IDLE),
+            _ => {}
+        }
+    }
+
+    fn disable(&self) -> Option<Transmission> {
+        self.in_flight.borrow_mut().take();
+
+        match self.state() {
+            Some(State::DISABLED) => return None,
+            Some(State::TX_RU | State::TX_IDLE | State::TX) => self.set_state(State::TX_DISABLE),
+            _ => self.set_state(State::RX_DISABLE),
+        }
+
+        self.set_state(State::DISABLED);
+        self.raise(Register::EventsDisabled);
+
+        let shortcuts = self.shortcuts();
+        if shortcuts.contains(Shortcuts::DISABLED_TXEN) {
+            self.enable(State::TX_RU, State::TX_IDLE)
+        } else if shortcuts.contains(Shortcuts::DISABLED_RXEN) {
+            self.enable(State::RX_RU, State::RX_IDLE)
+        } else {
+            None
+        }
+    }
+
+    fn sample_rssi(&self) {
+        self.set(Register::RssiSample, self.rssi.into());
+        self.raise(Register::EventsRssiEnd);
+    }
+
+    /// Raises `ADDRESS` and follows the shortcuts to `RSSISTART` and `BCSTART`
+    fn address(&self) {
+        self.raise(Register::EventsAddress);
+
+        let shortcuts = self.shortcuts();
+        if shortcuts.contains(Shortcuts::ADDRESS_RSSISTART) {
+            self.sample_rssi();
+        }
+        if shortcuts.contains(Shortcuts::ADDRESS_BCSTART) {
+            self.bit_counter.set(true);
+        }
+    }
+
+    /// Raises `BCMATCH` if the bit counter is running and reaches `BCC` within `bits`
+    fn count_bits(&self, bits: usize) {
+        if self.bit_counter.get() && reg_access::read_bcc(self) as usize <= bits {
+            self.raise(Register::EventsBcMatch);
+        }
+    }
+
+    /// Raises `END` and follows the shortcuts from it
+    fn end(&self) -> Option<Transmission> {
+        self.raise(Register::EventsEnd);
+
+        let shortcuts = self.shortcuts();
+        if shortcuts.contains(Shortcuts::END_DISABLE) {
+            self.disable()
+        } else if shortcuts.contains(Shortcuts::END_START) {
+            self.start()
+        } else {
+            None
+        }
+    }
+
+    /// Reads the packet to transmit from `PACKETPTR`
+    fn outgoing(&self) -> Transmission {
+        let config = reg_access::read_packet_config(self);
+        let address = self.air_address(reg_access::read_tx_address(self));
+
+        // SAFETY: the driver points PACKETPTR to a packet buffer before starting
+        let buf = unsafe { self.packet_buffer() };
+        let order = config.endian;
+        let mut bits = Bits::default();
+
+        let (s0, rest) = buf.split_at(config.s0_len.bytes());
+        if let Some(s0) = s0.first() {
+            bits.push((*s0).into(), 8, order);
+        }
+
+        let lf_bits = config.lf_len.bits().into();
+        let (length, rest) = rest.split_at(config.lf_len.bytes());
+        let length = read_field(length) & ((1u32 << lf_bits) - 1);
+        bits.push(length, lf_bits, order);
+
+        let (s1, rest) = rest.split_at(config.s1_len.bytes());
+        bits.push(read_field(s1), config.s1_len.bits().into(), order);
+
+        let payload = (length as usize + usize::from(config.statlen))
+            .min(config.maxlen.into())
+            .min(rest.len());
+        for byte in &rest[..payload] {
+            bits.push((*byte).into(), 8, order);
+        }
+
+        let crc = self.crc(&address, &bits.0);
+        bits.push(crc, 8 * self.crc_len(), Endianness::BIG);
+
+        self.whiten(&mut bits, &config);
+
+        Transmission {
+            frequency: reg_access::read_frequency(self),
+            mode: self.get(Register::Mode),
+            address,
+            bits,
+        }
+    }
+
+    /// Starts `packet`
+    fn fly(&self, packet: InFlight) {
+        *self.in_flight.borrow_mut() = Some(packet);
+        self.looks.set(0);
+    }
+
+    /// The driver looks for the end of the packet in flight, which ends it after
+    /// [`Self::airtime`] times. Returns what its end starts
+    fn look(&self) -> Option<Transmission> {
+        if self.in_flight.borrow().is_none() {
+            return None;
+        }
+
+        self.looks.set(self.looks.get() + 1);
+        if self.looks.get() < self.airtime {
+            self.progress();
+            return None;
+        }
+
+        self.finish()
+    }
+
+    /// Lets the packet being received get as far as the bit counter compares to, if it's running:
+    /// the bytes completed by then are written to memory, and `BCMATCH` is raised
+    fn progress(&self) {
+        let in_flight = self.in_flight.borrow();
+        let Some(InFlight::Rx { transmission, ptr }) = &*in_flight else {
+            return;
+        };
+
+        let bcc = reg_access::read_bcc(self) as usize;
+        if !self.bit_counter.get() || bcc > transmission.bits.0.len() {
+            return;
+        }
+
+        let decoded = self.decode(transmission, &reg_access::read_packet_config(self));
+        let len = decoded
+            .complete_at
+            .iter()
+            .take_while(|at| **at <= bcc)
+            .count();
+
+        // SAFETY: the driver points PACKETPTR to a packet buffer before starting, and the packet
+        // is capped at its size
+        unsafe { core::ptr::copy_nonoverlapping(decoded.memory.as_ptr(), *ptr as *mut u8, len) };
+
+        self.raise(Register::EventsBcMatch);
+    }
+
+    /// Ends the packet in flight, if there is one, returning what its end starts
+    fn finish(&self) -> Option<Transmission> {
+        let in_flight = self.in_flight.borrow_mut().take();
+
+        match in_flight? {
+            InFlight::Tx => {
+                self.raise(Register::EventsPayload);
+                self.set_state(State::TX_IDLE);
+
+                self.end()
+            }
+            InFlight::Rx { transmission, ptr } => self.receive(&transmission, ptr),
+        }
+    }
+
+    /// Runs the device address match unit on a received packet
+    fn match_device_address(&self, pdu: &[u8], config: &PacketConfig) {
+        let enabled = reg_access::read_dacnf_ena(self);
+        if enabled == 0 {
+            return;
+        }
+
+        let tx_add = config.s0_len.bytes() > 0 && pdu.first().is_some_and(|s0| s0 & (1 << 6) != 0);
+        let device = pdu
+            .get(config.header_len()..config.header_len() + 6)
+            .map(|a| {
+                let base = u32::from_le_bytes([a[0], a[1], a[2], a[3]]);
+                let prefix = u16::from_le_bytes([a[4], a[5]]);
+
+                (base, prefix)
+            });
+
+        let matched = device.and_then(|(base, prefix)| {
+            (0..crate::MAX_DEVICE_ADDRESSES).find(|&slot| {
+                enabled & (1 << slot) != 0
+                    && reg_access::read_dab(self, slot) == base
+                    && reg_access::read_dap(self, slot) == prefix
+                    && reg_access::read_dacnf_txadd(self, slot) == tx_add
+            })
+        });
+
+        match matched {
+            Some(slot) => {
+                self.set(Register::Dai, slot.into());
+                self.raise(Register::EventsDevMatch);
+            }
+            None => self.raise(Register::EventsDevMiss),
+        }
+    }
+
+    /// Starts receiving `transmission`, if the radio is listening for it
+    fn deliver(&self, transmission: &Transmission) {
+        if self.state() != Some(State::RX)
+            || self.in_flight.borrow().is_some()
+            || reg_access::read_frequency(self) != transmission.frequency
+            || self.get(Register::Mode) != transmission.mode
+        {
+            return;
+        }
+
+        let rx_addresses = reg_access::read_rx_address(self);
+        let Some(matched) = (0..8)
+            .find(|&a| rx_addresses & (1 << a) != 0 && self.air_address(a) == transmission.address)
+        else {
+            return;
+        };
+
+        self.set(Register::RxMatch, matched.into());
+        self.address();
+
+        // the packet goes to where PACKETPTR points when the address is received, even if the
+        // driver changes it afterwards
+        self.fly(InFlight::Rx {
+            transmission: transmission.clone(),
+            ptr: self.packet_ptr.get(),
+        });
+    }
+
+    /// Decodes the fields of `transmission`, the way the radio is configured
+    fn decode(&self, transmission: &Transmission, config: &PacketConfig) -> Decoded {
+        let order = config.endian;
+        let mut bits = transmission.bits.clone();
+        self.whiten(&mut bits, config);
+
+        let mut pos = 0;
+        let mut memory = Vec::new();
+        let mut complete_at = Vec::new();
+
+        // a field is complete once all of its bits have arrived
+        let mut field = |pos: &mut usize, bits_len: usize, bytes: usize| {
+            let value = bits.take(pos, bits_len, order);
+            memory.extend_from_slice(&value.to_le_bytes()[..bytes]);
+            complete_at.resize(memory.len(), *pos);
+
+            value
+        };
+
+        field(&mut pos, 8 * config.s0_len.bytes(), config.s0_len.bytes());
+        let length = field(&mut pos, config.lf_len.bits().into(), config.lf_len.bytes());
+        field(&mut pos, config.s1_len.bits().into(), config.s1_len.bytes());
+
+        let payload = (length as usize + usize::from(config.statlen)).min(config.maxlen.into());
+        for _ in 0..payload {
+            field(&mut pos, 8, 1);
+        }
+
+        memory.truncate(MAX_IN_MEMORY_PACKET_LENGTH);
+        complete_at.truncate(MAX_IN_MEMORY_PACKET_LENGTH);
+
+        let pdu = pos;
+        let crc = bits.take(&mut pos, 8 * self.crc_len(), Endianness::BIG);
+        let crc_ok = reg_access::read_crc_len(self) == CrcLength::DISABLED
+            || (pos == bits.0.len() && crc == self.crc(&transmission.address, &bits.0[..pdu]));
+
+        Decoded {
+            memory,
+            complete_at,
+            crc,
+            crc_ok,
+        }
+    }
+
+    /// Receives the rest of `transmission` into the buffer at `ptr`
+    fn receive(&self, transmission: &Transmission, ptr: usize) -> Option<Transmission> {
+        let config = reg_access::read_packet_config(self);
+        let decoded = self.decode(transmission, &config);
+        let memory = &decoded.memory;
+
+        // SAFETY: the driver points PACKETPTR to a packet buffer before starting, and the packet
+        // is capped at its size
+        unsafe { core::ptr::copy_nonoverlapping(memory.as_ptr(), ptr as *mut u8, memory.len()) };
+
+        self.match_device_address(memory, &config);
+        self.count_bits(transmission.bits.0.len());
+
+        self.set(Register::RxCrc, decoded.crc);
+        self.set(Register::CrcStatus, decoded.crc_ok.into());
+
+        self.raise(Register::EventsPayload);
+        self.set_state(State::RX_IDLE);
+
+        self.end()
+    }
+}
+
+impl RadioRegisters for Node {
+    fn read(&self, reg: Register) -> u32 {
+        match reg {
+            Register::IntEnClr => self.get(Register::IntEnSet),
+            _ => self.get(reg),
+        }
+    }
+
+    fn write(&self, reg: Register, value: u32) {
+        self.set(reg, value);
+    }
+}
+
+/// Locks `mutex`, carrying on even if a thread panicked while holding it
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
@@ -0,0 +1,264 @@
+//! Switching between receiving and transmitting, timed by the radio itself
+//!
+//! Protocols like BLE and ESB answer a packet a fixed time after it ends - the inter frame
+//! spacing, or `TIFS`. That's too short to be met by triggering tasks from the CPU, but with the
+//! `END_DISABLE` and `DISABLED_TXEN` (or `DISABLED_RXEN`) shortcuts set, the radio ramps up in
+//! the other direction as soon as a packet ends and starts [`Radio::tifs`] microseconds after it.
+//!
+//! [`Radio::receive_then_transmit`] and [`Radio::transmit_then_receive`] arm that switch while
+//! the first packet is in flight, and report with [`Timing`] whether they managed to do so
+//! before it ended. Either way, the radio is idle in the direction it started in afterwards, with
+//! the shortcuts it was set to before.
+
+use crate::{
+    Enabled, Event, Radio, RadioRegisters, Receiver, Shortcuts, State, Transmitter, clock::Clock,
+    cycle_deadline, packet, reg_access, time_deadline,
+};
+
+/// Whether the radio switched directions by itself
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Timing {
+    /// The switch was armed before the first packet ended, so the radio made it after the inter
+    /// frame spacing
+    InTime,
+    /// The first packet ended before the switch was armed, so it was triggered by the CPU
+    /// afterwards - later than the inter frame spacing
+    Late,
+}
+
+impl<T, R: RadioRegisters> Radio<Enabled<T>, R> {
+    /// Set the inter frame spacing in microseconds (`TIFS`)
+    ///
+    /// The radio only waits for it when it switches directions through shortcuts, and it can't
+    /// make the switch take less time than ramping up does.
+    pub fn set_tifs(&self, us: u8) -> &Self {
+        reg_access::write_tifs(&self.radio, us);
+
+        self
+    }
+
+    /// Get the inter frame spacing in microseconds
+    pub fn tifs(&self) -> u8 {
+        reg_access::read_tifs(&self.radio)
+    }
+
+    /// Starts the radio in the `idle` direction with the buffer at `first`, switches directions
+    /// once that packet ends and waits for the packet in the buffer at `second` to end, calling
+    /// `deadline` all the while. If it fails, the exchange is stopped and its error is returned
+    ///
+    /// The radio is back in `idle` afterwards, with the shortcuts restored.
+    ///
+    /// # Safety
+    ///
+    /// Both pointers MUST point to packet buffers which outlive the call.
+    unsafe fn turnaround(
+        &self,
+        first: *mut u8,
+        second: *mut u8,
+        idle: State,
+        mut deadline: impl FnMut() -> crate::Result<()>,
+    ) -> crate::Result<Timing> {
+        let rx_first = idle == State::RX_IDLE;
+        let (next, back) = if rx_first {
+            (Shortcuts::DISABLED_TXEN, Shortcuts::DISABLED_RXEN)
+        } else {
+            (Shortcuts::DISABLED_RXEN, Shortcuts::DISABLED_TXEN)
+        };
+        let enable_next: fn(&R) = if rx_first {
+            reg_access::enable_tx
+        } else {
+            reg_access::enable_rx
+        };
+        let enable_back: fn(&R) = if rx_first {
+            reg_access::enable_rx
+        } else {
+            reg_access::enable_tx
+        };
+        let other_idle = if rx_first {
+            State::TX_IDLE
+        } else {
+            State::RX_IDLE
+        };
+
+        let previous = self.shortcuts();
+        self.set_shortcuts(Shortcuts::END_DISABLE | Shortcuts::READY_START);
+
+        // SAFETY: guaranteed by the caller
+        unsafe { self.set_packet_ptr(first) };
+
+        for event in [
+            Event::Ready,
+            Event::Address,
+            Event::RSSIEnd,
+            Event::DevMatch,
+            Event::End,
+            Event::Disabled,
+        ] {
+            self.clear_event(event);
+        }
+        reg_access::tasks::start(&self.radio);
+
+        let arm = || {
+            // PACKETPTR is double buffered, the packet in flight keeps the buffer it started with
+            // SAFETY: guaranteed by the caller
+            unsafe { self.set_packet_ptr(second) };
+            self.set_shortcuts(Shortcuts::END_DISABLE | Shortcuts::READY_START | next);
+
+            // the shortcut is only taken if it's set before the radio gets disabled
+            if self.event_occurred(Event::Disabled) && self.get_state() == Ok(State::DISABLED) {
+                enable_next(&self.radio);
+                Timing::Late
+            } else {
+                Timing::InTime
+            }
+        };
+
+        // without shortcuts, stopping leaves the radio idle instead of starting it again
+        let mut poll = || {
+            deadline().inspect_err(|_| {
+                self.set_shortcuts(Shortcuts::empty());
+            })
+        };
+
+        let mut timing = None;
+        let r = self.wait_for_end(idle, || {
+            if timing.is_none() && self.event_occurred(Event::Address) {
+                timing = Some(arm());
+            }
+
+            poll()
+        });
+
+        if let Err(e) = r {
+            self.set_shortcuts(previous);
+            return Err(e);
+        }
+
+        let timing = timing.unwrap_or_else(arm);
+        self.clear_event(Event::End);
+
+        let mut returning = false;
+        let r = self.wait_for_end(other_idle, || {
+            // the radio has switched, so it can be sent back once the second packet ends
+            if !returning && self.event_occurred(Event::Ready) {
+                returning = true;
+                self.set_shortcuts(Shortcuts::END_DISABLE | back);
+            }
+
+            poll()
+        });
+
+        if r.is_ok() && returning {
+            self.wait_for_state(idle);
+        } else {
+            self.set_shortcuts(Shortcuts::empty());
+
+            reg_access::disable(&self.radio);
+            self.wait_for_state(State::DISABLED);
+            enable_back(&self.radio);
+            self.wait_for_state(idle);
+        }
+
+        self.set_shortcuts(previous);
+
+        r.map(|()| timing)
+    }
+}
+
+impl<R: RadioRegisters> Radio<Enabled<Transmitter>, R> {
+    /// Transmits `packet` and receives the answer to it, which the radio starts listening for
+    /// [`Self::tifs`] after the transmission ends. Waits for `cycles` CPU cycles until
+    /// returning [`crate::Error::TimedOut`]
+    ///
+    /// The answer is received on the addresses enabled with [`Radio::enable_rx_address`] while
+    /// the radio was a receiver, into the buffer `packet` was sent from. The radio is back in
+    /// [`State::TX_IDLE`] when this returns.
+    pub fn transmit_then_receive(
+        &self,
+        packet: &packet::Packet,
+        cycles: u32,
+    ) -> crate::Result<(packet::ReceivedPacket, Timing)> {
+        self.transmit_then_receive_inner(packet, cycle_deadline(cycles))
+    }
+
+    /// Like [`Self::transmit_then_receive`], but times out after `timeout` has passed on
+    /// `clock`. See [`crate::clock`] for how accurate that is
+    pub fn transmit_then_receive_within(
+        &self,
+        packet: &packet::Packet,
+        timeout: core::time::Duration,
+        clock: &mut impl Clock,
+    ) -> crate::Result<(packet::ReceivedPacket, Timing)> {
+        self.transmit_then_receive_inner(packet, time_deadline(timeout, clock))
+    }
+
+    fn transmit_then_receive_inner(
+        &self,
+        packet: &packet::Packet,
+        deadline: impl FnMut() -> crate::Result<()>,
+    ) -> crate::Result<(packet::ReceivedPacket, Timing)> {
+        let serialized = self.serialize_for_tx(packet)?;
+
+        let mut p = self.new_rx_packet()?;
+        p.load(serialized.buf());
+        let ptr = p.buf_mut_ptr();
+
+        // SAFETY: `p` lives on the stack until this function returns, and `turnaround` doesn't
+        // return until the radio is idle again
+        let timing = unsafe { self.turnaround(ptr, ptr, State::TX_IDLE, deadline) }?;
+
+        Ok((self.finish_rx_packet(p, None)?, timing))
+    }
+}
+
+impl<R: RadioRegisters> Radio<Enabled<Receiver>, R> {
+    /// Receives a packet and answers it with `response`, which the radio starts transmitting
+    /// [`Self::tifs`] after the received packet ends. Waits for `cycles` CPU cycles until
+    /// returning [`crate::Error::TimedOut`]
+    ///
+    /// There's no time to check the received packet before answering, so `response` goes out
+    /// even if the packet turns out to have a [`crate::Error::CrcMismatch`]. It's sent to the
+    /// logical address `TXADDRESS` was last set to. The radio is back in [`State::RX_IDLE`] when
+    /// this returns.
+    pub fn receive_then_transmit(
+        &self,
+        response: &packet::Packet,
+        cycles: u32,
+    ) -> crate::Result<(packet::ReceivedPacket, Timing)> {
+        self.receive_then_transmit_inner(response, cycle_deadline(cycles))
+    }
+
+    /// Like [`Self::receive_then_transmit`], but times out after `timeout` has passed on
+    /// `clock`. See [`crate::clock`] for how accurate that is
+    pub fn receive_then_transmit_within(
+        &self,
+        response: &packet::Packet,
+        timeout: core::time::Duration,
+        clock: &mut impl Clock,
+    ) -> crate::Result<(packet::ReceivedPacket, Timing)> {
+        self.receive_then_transmit_inner(response, time_deadline(timeout, clock))
+    }
+
+    fn receive_then_transmit_inner(
+        &self,
+        response: &packet::Packet,
+        deadline: impl FnMut() -> crate::Result<()>,
+    ) -> crate::Result<(packet::ReceivedPacket, Timing)> {
+        let mut p = self.new_rx_packet()?;
+        let mut response = self.serialize_for_tx(response)?;
+
+        // SAFETY: both buffers live on the stack until this function returns, and `turnaround`
+        // doesn't return until the radio is idle again
+        let timing = unsafe {
+            self.turnaround(
+                p.buf_mut_ptr(),
+                response.buf_mut().as_mut_ptr(),
+                State::RX_IDLE,
+                deadline,
+            )
+        }?;
+
+        Ok((self.finish_rx_packet(p, None)?, timing))
+    }
+}
@@ -0,0 +1,342 @@
+//! Runs the driver against the in-memory RADIO peripheral
+
+use std::{cell::Cell, time::Duration};
+
+use nrf51_radio::{
+    Address, DeviceAddress, Error, Frequency, Mode, PhysicalAddress, Radio, State,
+    ble::advertiser::{Advertiser, PduType},
+    mock::{Frame, MockRadio},
+    nrf24::{self, AddressWidth, Crc, DataRate, PayloadLength},
+    packet::PacketConfig,
+    preset::Preset,
+};
+
+#[test]
+fn typestate_transitions() {
+    let mock = MockRadio::new();
+
+    let radio = Radio::new_zeroed(&mock);
+    assert_eq!(mock.state(), Some(State::DISABLED));
+
+    let radio = radio.into_transmitter();
+    assert_eq!(mock.state(), Some(State::TX_IDLE));
+
+    let radio = radio.disable().into_receiver();
+    assert_eq!(mock.state(), Some(State::RX_IDLE));
+
+    let radio = radio.into_transmitter();
+    assert_eq!(radio.get_state(), Ok(State::TX_IDLE));
+}
+
+#[test]
+fn address_masks() {
+    let mock = MockRadio::new();
+    let radio = Radio::new_zeroed(&mock).into_receiver();
+
+    radio
+        .enable_rx_addresses(&[Address::A, Address::C, Address::H])
+        .disable_rx_address(Address::C);
+    assert_eq!(radio.rx_addresses(), 0b1000_0001);
+
+    radio.disable_all_rx_addresses();
+    assert_eq!(radio.rx_addresses(), 0);
+
+    let radio = radio.into_transmitter();
+    radio.set_tx_address(Address::F);
+    assert_eq!(radio.tx_address(), Address::F);
+}
+
+#[test]
+fn transmit_packet() {
+    let mock = MockRadio::new();
+    let radio = Radio::new_zeroed(&mock).into_transmitter();
+    radio
+        .set_packet_config(PacketConfig::default())
+        .expect("default config is valid");
+    radio.set_tx_address(Address::B);
+
+    let packet = radio
+        .packet_builder()
+        .payload(&[1, 2, 3])
+        .build()
+        .expect("packet is valid");
+    radio.transmit_packet(&packet).expect("transmission failed");
+
+    let frame = mock.transmitted().expect("nothing was transmitted");
+    assert_eq!(frame.bytes(), &[3, 1, 2, 3]);
+    assert_eq!(frame.address, Address::B);
+}
+
+#[test]
+fn static_length_is_left_out_of_length_field() {
+    let mock = MockRadio::new();
+    let radio = Radio::new_zeroed(&mock).into_transmitter();
+    radio
+        .set_packet_config(PacketConfig {
+            maxlen: 10,
+            statlen: 2,
+            ..PacketConfig::default()
+        })
+        .expect("config is valid");
+
+    let packet = radio
+        .packet_builder()
+        .payload(&[1, 2, 3])
+        .build()
+        .expect("packet is valid");
+    assert_eq!(packet.length_field(), Some(1));
+    assert_eq!(packet.payload(), &[1, 2, 3]);
+
+    radio.transmit_packet(&packet).expect("transmission failed");
+    let frame = mock.transmitted().expect("nothing was transmitted");
+    assert_eq!(frame.bytes(), &[1, 1, 2, 3]);
+
+    // the payload can't be shorter than the static length
+    assert_eq!(
+        radio.packet_builder().payload(&[1]).build().unwrap_err(),
+        Error::ValueOutOfBounds
+    );
+}
+
+#[test]
+fn packet_config_rejects_fields_pcnf0_cannot_hold() {
+    let config = PacketConfig {
+        lf_len: nrf51_radio::packet::LengthFieldLength::from_bits(16)
+            .expect("length fits the constant"),
+        ..PacketConfig::default()
+    };
+    assert_eq!(config.validate(), Err(Error::ValueOutOfBounds));
+
+    let config = PacketConfig {
+        s1_len: nrf51_radio::packet::S1FieldLength::from_bits(16)
+            .expect("length fits the constant"),
+        ..PacketConfig::default()
+    };
+    assert_eq!(config.validate(), Err(Error::ValueOutOfBounds));
+
+    let config = PacketConfig {
+        lf_len: nrf51_radio::packet::LengthFieldLength::from_bits(15)
+            .expect("length fits the constant"),
+        s1_len: nrf51_radio::packet::S1FieldLength::from_bits(15)
+            .expect("length fits the constant"),
+        maxlen: 16,
+        ..PacketConfig::default()
+    };
+    assert_eq!(config.validate(), Ok(()));
+}
+
+#[test]
+fn big_endian_fields_are_little_endian_in_memory() {
+    let mock = MockRadio::new();
+    let radio = Radio::new_zeroed(&mock).into_transmitter();
+
+    // the 9-bit packet control field of an nRF24L01+ goes out most significant bit first
+    let config = nrf24::Config {
+        channel: 2,
+        data_rate: DataRate::Mbit2,
+        address_width: AddressWidth::Five,
+        crc: Crc::TwoBytes,
+        payload: PayloadLength::Static(2),
+    };
+    radio.apply_nrf24(&config).expect("configuration is valid");
+
+    let packet = radio
+        .packet_builder()
+        .s1(0x103)
+        .payload(&[7, 8])
+        .build()
+        .expect("packet is valid");
+    assert_eq!(packet.s1(), Some(0x103));
+
+    radio.transmit_packet(&packet).expect("transmission failed");
+    let frame = mock.transmitted().expect("nothing was transmitted");
+    assert_eq!(frame.bytes(), &[0x03, 0x01, 7, 8]);
+}
+
+#[test]
+fn receive_packet() {
+    let mock = MockRadio::new();
+    let radio = Radio::new_zeroed(&mock).into_receiver();
+    radio
+        .set_packet_config(PacketConfig::default())
+        .expect("default config is valid");
+    radio.enable_rx_address(Address::D);
+
+    let mut frame = Frame::new(&[2, 0xAB, 0xCD]).expect("frame fits");
+    frame.address = Address::D;
+    mock.queue_rx(frame);
+
+    let received = radio
+        .receive_packet_with_timeout(1000)
+        .expect("reception failed");
+    assert_eq!(received.address(), Address::D);
+    assert_eq!(received.packet().payload(), &[0xAB, 0xCD]);
+    assert_eq!(mock.state(), Some(State::RX_IDLE));
+}
+
+#[test]
+fn receive_times_out_on_other_address() {
+    let mock = MockRadio::new();
+    let radio = Radio::new_zeroed(&mock).into_receiver();
+    radio.enable_rx_address(Address::A);
+
+    let mut frame = Frame::new(&[0]).expect("frame fits");
+    frame.address = Address::B;
+    mock.queue_rx(frame);
+
+    assert!(matches!(
+        radio.receive_packet_with_timeout(100),
+        Err(Error::TimedOut)
+    ));
+    assert_eq!(mock.state(), Some(State::RX_IDLE));
+}
+
+#[test]
+fn receive_times_out_on_clock() {
+    let mock = MockRadio::new();
+    let radio = Radio::new_zeroed(&mock).into_receiver();
+
+    let mut now = 0;
+    let mut clock = || {
+        now += 10;
+        now
+    };
+
+    assert!(matches!(
+        radio.receive_packet_within(Duration::from_micros(500), &mut clock),
+        Err(Error::TimedOut)
+    ));
+    // one reading to start the timeout, then one for every 10 µs
+    assert_eq!(now, 510);
+    assert_eq!(mock.state(), Some(State::RX_IDLE));
+}
+
+#[test]
+fn presets_are_recognised() {
+    let mock = MockRadio::new();
+    let radio = Radio::new_zeroed(&mock).into_transmitter();
+    assert_eq!(radio.preset(), None);
+
+    for preset in [Preset::Ble1M, Preset::EsbDynamic2M] {
+        radio.apply_preset(preset).expect("preset is valid");
+        assert_eq!(radio.mode(), preset.mode());
+        assert_eq!(radio.preset(), Some(preset));
+    }
+
+    // the ESB presets only differ in the mode
+    radio.set_mode(Mode::NRF_1MBIT);
+    assert_eq!(radio.preset(), Some(Preset::EsbDynamic1M));
+
+    radio.set_whitening(true);
+    assert!(!radio.matches_preset(Preset::EsbDynamic1M));
+    assert_eq!(radio.preset(), None);
+}
+
+#[test]
+fn nrf24_configuration_is_translated() {
+    let mock = MockRadio::new();
+    let radio = Radio::new_zeroed(&mock).into_transmitter();
+
+    // the nRF24L01+ reset values, with dynamic payload length
+    let mut config = nrf24::Config {
+        channel: 2,
+        data_rate: DataRate::from_rf_setup(0x0F).expect("data rate is valid"),
+        address_width: AddressWidth::from_setup_aw(0x03).expect("width is valid"),
+        crc: Crc::from_config(0x0C),
+        payload: PayloadLength::from_registers(0x04, 0),
+    };
+    radio.apply_nrf24(&config).expect("configuration is valid");
+    assert_eq!(radio.preset(), Some(Preset::EsbDynamic2M));
+    assert_eq!(
+        radio.frequency(),
+        Frequency::from_mhz(2402).expect("in range")
+    );
+
+    radio
+        .set_nrf24_address(Address::B, &[0x01, 0x02, 0x03, 0x04, 0x05])
+        .expect("width matches");
+    assert_eq!(
+        radio.physical_address(Address::B),
+        Some(PhysicalAddress {
+            base: 0x40C0_20A0,
+            prefix: 0x80,
+        })
+    );
+    assert!(matches!(
+        radio.set_nrf24_address(Address::B, &[0x01, 0x02, 0x03]),
+        Err(Error::ValueOutOfBounds)
+    ));
+
+    config.channel = 101;
+    assert!(matches!(
+        radio.apply_nrf24(&config),
+        Err(Error::ValueOutOfBounds)
+    ));
+
+    config.channel = 2;
+    config.payload = PayloadLength::Static(0);
+    assert!(matches!(
+        radio.apply_nrf24(&config),
+        Err(Error::ValueOutOfBounds)
+    ));
+}
+
+#[test]
+fn advertising_event_ends_on_channel_39() {
+    let mock = MockRadio::new();
+    let address = DeviceAddress {
+        base: 0x4433_2211,
+        prefix: 0x6655,
+        tx_add: true,
+    };
+    let interval = Duration::from_millis(20);
+
+    let radio = Radio::new_zeroed(&mock).into_transmitter();
+    let advertiser = Advertiser::new(radio, address, PduType::NonConnectable, &[0; 32], interval);
+    assert!(matches!(advertiser, Err(Error::ValueOutOfBounds)));
+
+    let radio = Radio::new_zeroed(&mock).into_transmitter();
+    let short = Duration::from_millis(19);
+    let advertiser = Advertiser::new(radio, address, PduType::NonConnectable, &[], short);
+    assert!(matches!(advertiser, Err(Error::ValueOutOfBounds)));
+
+    let radio = Radio::new_zeroed(&mock).into_transmitter();
+    let mut advertiser = Advertiser::new(radio, address, PduType::Scannable, &[2, 1, 6], interval)
+        .expect("advertiser is valid");
+
+    // the clock moves 100 µs on every reading
+    let now = Cell::new(0);
+    let mut clock = || {
+        now.set(now.get() + 100);
+        now.get()
+    };
+    let mut random = || 12_345;
+
+    advertiser
+        .advertise(&mut clock, &mut random)
+        .expect("advertising failed");
+    let frame = mock.transmitted().expect("nothing was transmitted");
+    assert_eq!(
+        frame.bytes(),
+        &[0x46, 9, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 2, 1, 6]
+    );
+
+    let radio = advertiser.free();
+    assert_eq!(
+        radio.frequency(),
+        Frequency::from_mhz(2480).expect("in range")
+    );
+    assert_eq!(radio.whitening_iv(), 39);
+
+    // the next event waits for the interval and a delay of 12345 % 10001 µs
+    let mut advertiser = Advertiser::new(radio, address, PduType::Scannable, &[], interval)
+        .expect("advertiser is valid");
+    advertiser
+        .advertise(&mut clock, &mut random)
+        .expect("advertising failed");
+    let start = now.get();
+    advertiser
+        .advertise(&mut clock, &mut random)
+        .expect("advertising failed");
+    assert!(now.get() >= start + 20_000 + 2_344);
+}
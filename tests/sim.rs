@@ -0,0 +1,893 @@
+//! Exchanges packets between simulated radios
+
+use std::{
+    pin::pin,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    task::{Context, Poll, Wake, Waker},
+    thread,
+    time::{Duration, Instant},
+};
+
+use nrf51_radio::{
+    Address, CrcConfig, CrcLength, DeviceAddress, Disabled, Enabled, Error, Frequency,
+    PhysicalAddress, Radio, Receiver, State,
+    ble::{
+        AdvertisingChannel,
+        advertiser::{Advertiser, PduType},
+    },
+    esb::{self, PacketControl, Prx, Ptx},
+    gazell::{self, ChannelTable, Device, Host},
+    nrf24::{self, AddressWidth, Crc, DataRate, PayloadLength},
+    packet::{LengthFieldLength, PacketConfig, ReceivedPacket, S1FieldLength},
+    preset::Preset,
+    registers::{RadioRegisters, Register},
+    sim::{Air, SimRadio},
+    turnaround::Timing,
+};
+
+const CRC: CrcConfig = CrcConfig {
+    len: CrcLength::TWO,
+    skip_address: false,
+    polynomial: 0x1021,
+    init: 0xFFFF,
+};
+
+/// Configures a radio the way all tests share, on 2440 MHz
+fn radio(sim: &SimRadio, whiten: bool) -> Radio<Disabled, &SimRadio> {
+    let radio = Radio::new_zeroed(sim).into_transmitter();
+
+    radio
+        .set_packet_config(PacketConfig {
+            whiten,
+            ..Default::default()
+        })
+        .expect("config is valid");
+    radio
+        .set_physical_address(
+            Address::A,
+            PhysicalAddress {
+                base: 0xE7E7_E7E7,
+                prefix: 0xE7,
+            },
+        )
+        .expect("address fits");
+    radio.set_crc_config(CRC).expect("CRC config is valid");
+    radio.set_frequency(Frequency::from_mhz(2440).expect("frequency is in range"));
+
+    radio.disable()
+}
+
+/// Spins until `sim` is in [`State::RX`], ready to receive what's sent next
+fn wait_until_listening(sim: &SimRadio) {
+    while sim.state() != Some(State::RX) {
+        thread::yield_now();
+    }
+}
+
+/// Polls `future` to completion, handling the interrupts of `sim` while it's pending, as the
+/// `RADIO` interrupt handler would. The future is only polled again once the handler woke it
+fn block_on<F: Future>(sim: &SimRadio, future: F) -> F::Output {
+    struct Woken(AtomicBool);
+
+    impl Wake for Woken {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    let woken = Arc::new(Woken(AtomicBool::new(true)));
+    let waker = Waker::from(woken.clone());
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    let start = Instant::now();
+
+    loop {
+        if woken.0.swap(false, Ordering::SeqCst)
+            && let Poll::Ready(output) = future.as_mut().poll(&mut cx)
+        {
+            return output;
+        }
+
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "future was never woken"
+        );
+        nrf51_radio::on_interrupt_with(sim);
+        thread::yield_now();
+    }
+}
+
+/// Transmits `payload` from `tx` while `rx` listens, returning what `rx` received
+fn exchange(
+    tx: Radio<Disabled, &SimRadio>,
+    rx: Radio<Disabled, &SimRadio>,
+    rx_sim: &SimRadio,
+    payload: &[u8],
+) -> nrf51_radio::Result<ReceivedPacket> {
+    exchange_with(tx, rx, rx_sim, payload, |rx| {
+        rx.receive_packet_with_timeout(100_000)
+    })
+}
+
+/// Like [`exchange`], but `rx` listens with `receive`
+fn exchange_with(
+    tx: Radio<Disabled, &SimRadio>,
+    rx: Radio<Disabled, &SimRadio>,
+    rx_sim: &SimRadio,
+    payload: &[u8],
+    receive: impl FnOnce(&Radio<Enabled<Receiver>, &SimRadio>) -> nrf51_radio::Result<ReceivedPacket>
+    + Send,
+) -> nrf51_radio::Result<ReceivedPacket> {
+    thread::scope(|s| {
+        let receiver = s.spawn(move || {
+            let rx = rx.into_receiver();
+            rx.enable_rx_address(Address::A);
+            receive(&rx)
+        });
+
+        wait_until_listening(rx_sim);
+
+        let tx = tx.into_transmitter();
+        tx.set_tx_address(Address::A);
+        let packet = tx
+            .packet_builder()
+            .payload(payload)
+            .build()
+            .expect("packet is valid");
+        tx.transmit_packet(&packet).expect("transmission failed");
+
+        receiver.join().expect("receiver panicked")
+    })
+}
+
+#[test]
+fn packet_goes_over_the_air() {
+    let air = Air::new();
+    let (a, b) = (air.radio(), air.radio());
+
+    let received =
+        exchange(radio(&a, false), radio(&b, false), &b, &[1, 2, 3, 4]).expect("nothing received");
+
+    assert_eq!(received.packet().payload(), &[1, 2, 3, 4]);
+    assert_eq!(received.address(), Address::A);
+    assert!(received.crc().is_some());
+}
+
+#[test]
+fn whitened_packet_goes_over_the_air() {
+    let air = Air::new();
+    let (a, b) = (air.radio(), air.radio());
+
+    let received =
+        exchange(radio(&a, true), radio(&b, true), &b, &[5, 6, 7]).expect("nothing received");
+
+    assert_eq!(received.packet().payload(), &[5, 6, 7]);
+}
+
+#[test]
+fn other_frequency_is_not_heard() {
+    let air = Air::new();
+    let (a, b) = (air.radio(), air.radio());
+
+    let rx = radio(&b, false).into_transmitter();
+    rx.set_frequency(Frequency::from_mhz(2480).expect("frequency is in range"));
+
+    let received = exchange(radio(&a, false), rx.disable(), &b, &[1]);
+    assert!(matches!(received, Err(Error::TimedOut)));
+}
+
+#[test]
+fn other_address_is_not_heard() {
+    let air = Air::new();
+    let (a, b) = (air.radio(), air.radio());
+
+    let rx = radio(&b, false).into_transmitter();
+    rx.set_physical_address(
+        Address::A,
+        PhysicalAddress {
+            base: 0xC2C2_C2C2,
+            prefix: 0xE7,
+        },
+    )
+    .expect("address fits");
+
+    let received = exchange(radio(&a, false), rx.disable(), &b, &[1]);
+    assert!(matches!(received, Err(Error::TimedOut)));
+}
+
+#[test]
+fn crc_mismatch_is_reported() {
+    let air = Air::new();
+    let (a, b) = (air.radio(), air.radio());
+
+    let rx = radio(&b, false).into_transmitter();
+    rx.set_crc_config(CrcConfig { init: 0, ..CRC })
+        .expect("CRC config is valid");
+
+    let received = exchange(radio(&a, false), rx.disable(), &b, &[1]);
+    assert!(matches!(received, Err(Error::CrcMismatch)));
+}
+
+#[test]
+fn device_address_match_reports_slot() {
+    let air = Air::new();
+    let (a, b) = (air.radio(), air.radio());
+
+    let rx = radio(&b, false).into_receiver();
+    rx.set_device_address(
+        5,
+        DeviceAddress {
+            base: 0x4433_2211,
+            prefix: 0x6655,
+            tx_add: false,
+        },
+    )
+    .and_then(|rx| rx.enable_device_address(5))
+    .expect("slot is in range");
+
+    let payload = [0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77];
+    let received =
+        exchange(radio(&a, false), rx.disable(), &b, &payload).expect("nothing received");
+    assert_eq!(received.device_address(), Some(5));
+
+    let other = [0x11, 0x22, 0x33, 0x44, 0x55, 0x00, 0x77];
+    let rx = radio(&b, false).into_receiver();
+    rx.enable_device_address(5).expect("slot is in range");
+    let received = exchange(radio(&a, false), rx.disable(), &b, &other).expect("nothing received");
+    assert_eq!(received.device_address(), None);
+}
+
+#[test]
+fn inspected_header_rejects_packet() {
+    let air = Air::new();
+    let (a, b) = (air.radio(), air.radio());
+    // the packet stays in flight long enough to be inspected and rejected before it ends
+    b.set_airtime(3);
+
+    // the length field and the first byte of the payload
+    let inspect = |rx: &Radio<Enabled<Receiver>, &SimRadio>| {
+        rx.receive_packet_inspecting(100_000, 16, |p| p.payload().first() == Some(&0x01))
+    };
+
+    let received = exchange_with(
+        radio(&a, false),
+        radio(&b, false),
+        &b,
+        &[0x01, 0x02],
+        inspect,
+    )
+    .expect("nothing received");
+    assert_eq!(received.packet().payload(), &[0x01, 0x02]);
+
+    let rejected = exchange_with(
+        radio(&a, false),
+        radio(&b, false),
+        &b,
+        &[0x02, 0x01],
+        inspect,
+    );
+    assert_eq!(rejected.unwrap_err(), Error::Rejected);
+    assert_eq!(b.read(Register::EventsEnd), 0);
+}
+
+#[test]
+fn inspection_sees_payload_behind_sub_byte_header() {
+    let air = Air::new();
+    let (a, b) = (air.radio(), air.radio());
+    b.set_airtime(3);
+
+    // 4 bits of `LENGTH` and 4 of `S1` go over the air in one byte, but take two in memory
+    let config = PacketConfig {
+        lf_len: LengthFieldLength::from_bits(4).expect("length fits"),
+        s1_len: S1FieldLength::from_bits(4).expect("length fits"),
+        maxlen: 15,
+        ..Default::default()
+    };
+    let configure = |sim| {
+        let radio = radio(sim, false).into_transmitter();
+        radio.set_packet_config(config).expect("config is valid");
+        radio.disable()
+    };
+
+    // the header and the first byte of the payload
+    let inspect = |rx: &Radio<Enabled<Receiver>, &SimRadio>| {
+        rx.receive_packet_inspecting(100_000, 16, |p| p.payload().first() == Some(&0x01))
+    };
+
+    let received = exchange_with(configure(&a), configure(&b), &b, &[0x01, 0x02], inspect)
+        .expect("nothing received");
+    assert_eq!(received.packet().payload(), &[0x01, 0x02]);
+}
+
+#[test]
+fn async_transmit_and_receive() {
+    let air = Air::new();
+    let (a, b) = (air.radio(), air.radio());
+
+    let received = thread::scope(|s| {
+        let receiver = s.spawn(|| {
+            let mut rx = radio(&b, false).into_receiver();
+            rx.enable_rx_address(Address::A);
+            block_on(&b, rx.receive())
+        });
+
+        wait_until_listening(&b);
+
+        let tx = radio(&a, false).into_transmitter();
+        tx.set_tx_address(Address::A);
+        let packet = tx
+            .packet_builder()
+            .payload(&[1, 2])
+            .build()
+            .expect("packet is valid");
+        tx.transmit_packet(&packet).expect("transmission failed");
+
+        receiver.join().expect("receiver panicked")
+    })
+    .expect("nothing received");
+    assert_eq!(received.packet().payload(), &[1, 2]);
+
+    let received = thread::scope(|s| {
+        let receiver = s.spawn(|| {
+            let rx = radio(&b, false).into_receiver();
+            rx.enable_rx_address(Address::A);
+            rx.receive_packet_with_timeout(100_000)
+        });
+
+        wait_until_listening(&b);
+
+        let mut tx = radio(&a, false).into_transmitter();
+        tx.set_tx_address(Address::A);
+        let packet = tx
+            .packet_builder()
+            .payload(&[3, 4])
+            .build()
+            .expect("packet is valid");
+        block_on(&a, tx.transmit(&packet)).expect("transmission failed");
+
+        receiver.join().expect("receiver panicked")
+    })
+    .expect("nothing received");
+    assert_eq!(received.packet().payload(), &[3, 4]);
+}
+
+#[test]
+fn turnaround_answers_packet() {
+    let air = Air::new();
+    let (a, b) = (air.radio(), air.radio());
+
+    let rx = radio(&b, false).into_receiver();
+    rx.enable_rx_address(Address::A).set_tifs(150);
+
+    let tx = radio(&a, false).into_receiver();
+    tx.enable_rx_address(Address::A);
+    let tx = tx.into_transmitter();
+    tx.set_tx_address(Address::A).set_tifs(150);
+
+    thread::scope(|s| {
+        let responder = s.spawn(|| {
+            // TXADDRESS is still zeroed, which is logical address A
+            let response = rx
+                .packet_builder()
+                .payload(&[0xAC])
+                .build()
+                .expect("packet is valid");
+            rx.receive_then_transmit(&response, 1_000_000)
+        });
+
+        wait_until_listening(&b);
+
+        let packet = tx
+            .packet_builder()
+            .payload(&[1, 2, 3])
+            .build()
+            .expect("packet is valid");
+        let (answer, timing) = tx
+            .transmit_then_receive(&packet, 1_000_000)
+            .expect("no answer");
+        assert_eq!(answer.packet().payload(), &[0xAC]);
+        assert_eq!(timing, Timing::InTime);
+
+        let (received, timing) = responder
+            .join()
+            .expect("responder panicked")
+            .expect("nothing received");
+        assert_eq!(received.packet().payload(), &[1, 2, 3]);
+        assert_eq!(timing, Timing::InTime);
+    });
+
+    assert_eq!(a.state(), Some(State::TX_IDLE));
+    assert_eq!(b.state(), Some(State::RX_IDLE));
+}
+
+#[test]
+fn turnaround_is_late_when_packet_ends_first() {
+    let air = Air::new();
+    let (a, b) = (air.radio(), air.radio());
+    // the packet ends before the driver gets to see ADDRESS
+    a.set_airtime(0);
+
+    let tx = radio(&a, false).into_receiver();
+    tx.enable_rx_address(Address::A);
+    let tx = tx.into_transmitter();
+    tx.set_tx_address(Address::A);
+
+    let responder = radio(&b, false).into_transmitter();
+    responder.set_tx_address(Address::A);
+
+    thread::scope(|s| {
+        let exchange = s.spawn(|| {
+            let packet = tx
+                .packet_builder()
+                .payload(&[1, 2, 3])
+                .build()
+                .expect("packet is valid");
+            tx.transmit_then_receive(&packet, 1_000_000)
+        });
+
+        wait_until_listening(&a);
+
+        let answer = responder
+            .packet_builder()
+            .payload(&[0xAC])
+            .build()
+            .expect("packet is valid");
+        responder.transmit_packet(&answer).expect("answer not sent");
+
+        let (answer, timing) = exchange
+            .join()
+            .expect("exchange panicked")
+            .expect("no answer");
+        assert_eq!(answer.packet().payload(), &[0xAC]);
+        assert_eq!(timing, Timing::Late);
+    });
+
+    assert_eq!(a.state(), Some(State::TX_IDLE));
+}
+
+/// Configures a radio for ESB at 2 Mbit/s on 2440 MHz
+fn esb_radio(sim: &SimRadio) -> Radio<Disabled, &SimRadio> {
+    let radio = Radio::new_zeroed(sim).into_transmitter();
+
+    radio
+        .apply_preset(Preset::EsbDynamic2M)
+        .expect("preset is valid");
+    radio
+        .set_physical_address(
+            Address::A,
+            PhysicalAddress {
+                base: 0xE7E7_E7E7,
+                prefix: 0xE7,
+            },
+        )
+        .expect("address fits");
+    radio.set_frequency(Frequency::from_mhz(2440).expect("frequency is in range"));
+
+    radio.disable()
+}
+
+/// Microseconds since the clock was constructed
+fn wall_clock() -> impl FnMut() -> u64 {
+    let start = Instant::now();
+
+    move || start.elapsed().as_micros() as u64
+}
+
+#[test]
+fn esb_packet_is_acknowledged() {
+    let air = Air::new();
+    let (a, b) = (air.radio(), air.radio());
+
+    let mut prx = Prx::new(esb_radio(&b).into_receiver(), &[Address::A]).expect("layout is ESB");
+    let config = esb::Config {
+        ack_timeout: Duration::from_millis(500),
+        ..Default::default()
+    };
+    let mut ptx = Ptx::new(esb_radio(&a).into_transmitter(), config).expect("layout is ESB");
+
+    thread::scope(|s| {
+        let receiver = s.spawn(move || prx.receive(10_000_000));
+
+        wait_until_listening(&b);
+
+        let ack = ptx
+            .send(Address::A, &[1, 2, 3], &mut wall_clock())
+            .expect("not acknowledged");
+        assert_eq!(ack.retransmits(), 0);
+
+        let received = receiver
+            .join()
+            .expect("receiver panicked")
+            .expect("nothing received");
+        assert_eq!(received.pipe(), Address::A);
+        assert_eq!(received.payload(), &[1, 2, 3]);
+        assert_eq!(
+            received.control(),
+            PacketControl {
+                pid: 0,
+                no_ack: false
+            }
+        );
+    });
+}
+
+#[test]
+fn esb_packet_without_receiver_is_not_acknowledged() {
+    let air = Air::new();
+    let a = air.radio();
+
+    let config = esb::Config {
+        retransmits: 2,
+        retransmit_delay: Duration::from_micros(10),
+        ack_timeout: Duration::from_micros(100),
+    };
+    let mut ptx = Ptx::new(esb_radio(&a).into_transmitter(), config).expect("layout is ESB");
+
+    let result = ptx.send(Address::A, &[1], &mut wall_clock());
+    assert_eq!(result.unwrap_err(), Error::NotAcknowledged);
+    assert_eq!(a.state(), Some(State::TX_IDLE));
+}
+
+#[test]
+fn esb_duplicates_are_dropped() {
+    let air = Air::new();
+    let (a, b) = (air.radio(), air.radio());
+
+    let mut prx = Prx::new(esb_radio(&b).into_receiver(), &[Address::A]).expect("layout is ESB");
+    let tx = esb_radio(&a).into_transmitter();
+    tx.set_tx_address(Address::A);
+
+    // the same packet twice, as if the acknowledgement had been lost
+    let control = PacketControl {
+        pid: 2,
+        no_ack: true,
+    };
+    let packet = tx
+        .packet_builder()
+        .s1(control.to_s1())
+        .payload(&[7])
+        .build()
+        .expect("packet is valid");
+
+    thread::scope(|s| {
+        let receiver = s.spawn(move || (prx.receive(10_000_000), prx.receive(10_000_000)));
+
+        for _ in 0..2 {
+            wait_until_listening(&b);
+            tx.transmit_packet(&packet).expect("transmission failed");
+        }
+
+        let (first, second) = receiver.join().expect("receiver panicked");
+        assert_eq!(first.expect("nothing received").control(), control);
+        assert_eq!(second.unwrap_err(), Error::TimedOut);
+    });
+}
+
+#[test]
+fn esb_corrupted_packet_is_skipped() {
+    let air = Air::new();
+    let (a, b, c) = (air.radio(), air.radio(), air.radio());
+
+    let mut prx = Prx::new(esb_radio(&b).into_receiver(), &[Address::A]).expect("layout is ESB");
+    let config = esb::Config {
+        ack_timeout: Duration::from_millis(50),
+        ..Default::default()
+    };
+    let mut ptx = Ptx::new(esb_radio(&a).into_transmitter(), config).expect("layout is ESB");
+
+    // sends with a CRC the receiver doesn't agree with
+    let noise = esb_radio(&c).into_transmitter();
+    noise.set_tx_address(Address::A);
+    noise
+        .set_crc_config(CrcConfig {
+            init: 0,
+            ..noise.crc_config()
+        })
+        .expect("CRC config is valid");
+    let corrupted = noise
+        .packet_builder()
+        .s1(PacketControl::default().to_s1())
+        .payload(&[1])
+        .build()
+        .expect("packet is valid");
+
+    thread::scope(|s| {
+        let receiver = s.spawn(move || prx.receive(10_000_000));
+
+        wait_until_listening(&b);
+        noise
+            .transmit_packet(&corrupted)
+            .expect("transmission failed");
+
+        ptx.send(Address::A, &[2], &mut wall_clock())
+            .expect("not acknowledged");
+
+        let received = receiver
+            .join()
+            .expect("receiver panicked")
+            .expect("nothing received");
+        assert_eq!(received.payload(), &[2]);
+    });
+}
+
+#[test]
+fn esb_timeout_during_acknowledgement_keeps_packet() {
+    let air = Air::new();
+    let (a, b) = (air.radio(), air.radio());
+
+    let mut prx = Prx::new(esb_radio(&b).into_receiver(), &[Address::A]).expect("layout is ESB");
+    let config = esb::Config {
+        ack_timeout: Duration::from_millis(500),
+        ..Default::default()
+    };
+    let mut ptx = Ptx::new(esb_radio(&a).into_transmitter(), config).expect("layout is ESB");
+
+    thread::scope(|s| {
+        let receiver = s.spawn(|| {
+            // time runs out as soon as the acknowledgement starts going out
+            let mut clock = || {
+                if b.state() == Some(State::TX) {
+                    1_000_000
+                } else {
+                    0
+                }
+            };
+            prx.receive_within(Duration::from_secs(1), &mut clock)
+        });
+
+        wait_until_listening(&b);
+
+        ptx.send(Address::A, &[1, 2, 3], &mut wall_clock())
+            .expect("not acknowledged");
+
+        let received = receiver
+            .join()
+            .expect("receiver panicked")
+            .expect("nothing received");
+        assert_eq!(received.payload(), &[1, 2, 3]);
+    });
+}
+
+#[test]
+fn esb_ack_payloads_are_delivered() {
+    let air = Air::new();
+    let (a, b) = (air.radio(), air.radio());
+
+    let mut prx = Prx::new(esb_radio(&b).into_receiver(), &[Address::A]).expect("layout is ESB");
+    for i in 0..esb::ACK_QUEUE_DEPTH as u8 {
+        prx.queue_ack_payload(Address::A, &[i])
+            .expect("queue has room");
+    }
+    assert!(prx.ack_queue_full(Address::A));
+    assert_eq!(
+        prx.queue_ack_payload(Address::A, &[0]).unwrap_err(),
+        Error::QueueFull
+    );
+
+    prx.flush_ack_payloads(Address::A);
+    assert!(!prx.ack_queue_full(Address::A));
+    prx.queue_ack_payload(Address::A, &[9, 9])
+        .expect("queue has room");
+    prx.queue_ack_payload(Address::A, &[8])
+        .expect("queue has room");
+
+    let config = esb::Config {
+        ack_timeout: Duration::from_millis(500),
+        ..Default::default()
+    };
+    let mut ptx = Ptx::new(esb_radio(&a).into_transmitter(), config).expect("layout is ESB");
+
+    thread::scope(|s| {
+        let receiver = s.spawn(move || {
+            for _ in 0..3 {
+                prx.receive(10_000_000).expect("nothing received");
+            }
+        });
+
+        for expected in [&[9, 9][..], &[8], &[]] {
+            wait_until_listening(&b);
+
+            let ack = ptx
+                .send(Address::A, &[1], &mut wall_clock())
+                .expect("not acknowledged");
+            assert_eq!(ack.payload(), expected);
+        }
+
+        receiver.join().expect("receiver panicked");
+    });
+}
+
+#[test]
+fn nrf24_static_payload_goes_over_the_air() {
+    let air = Air::new();
+    let (a, b) = (air.radio(), air.radio());
+
+    let config = nrf24::Config {
+        channel: 76,
+        data_rate: DataRate::Mbit1,
+        address_width: AddressWidth::Three,
+        crc: Crc::OneByte,
+        payload: PayloadLength::Static(4),
+    };
+    let setup = |sim| {
+        let radio = Radio::new_zeroed(sim).into_transmitter();
+        radio.apply_nrf24(&config).expect("configuration is valid");
+        radio
+            .set_nrf24_address(Address::A, &[0xB3, 0xB4, 0xB5])
+            .expect("width matches");
+        radio.disable()
+    };
+
+    let received = exchange(setup(&a), setup(&b), &b, &[4, 3, 2, 1]).expect("nothing received");
+    assert_eq!(received.packet().payload(), &[4, 3, 2, 1]);
+    assert!(received.crc().is_some());
+}
+
+/// Gazell timing slow enough for the simulator, hopping between three channels
+fn gazell_config() -> gazell::Config {
+    let channels = [2410, 2420, 2430].map(|mhz| Frequency::from_mhz(mhz).expect("in range"));
+
+    gazell::Config {
+        channels: ChannelTable::new(&channels).expect("table fits"),
+        timeslot_period: Duration::from_millis(20),
+        timeslots_per_channel: 2,
+        timeslots_per_channel_out_of_sync: 8,
+        sync_lifetime: 12,
+        max_tx_attempts: 100,
+    }
+}
+
+#[test]
+fn gazell_device_reaches_host() {
+    let air = Air::new();
+    let (a, b) = (air.radio(), air.radio());
+
+    let mut host = Host::new(
+        esb_radio(&b).into_receiver(),
+        &[Address::A],
+        gazell_config(),
+    )
+    .expect("layout is ESB");
+    host.queue_ack_payload(Address::A, &[5])
+        .expect("queue has room");
+    let mut device =
+        Device::new(esb_radio(&a).into_transmitter(), gazell_config()).expect("layout is ESB");
+
+    thread::scope(|s| {
+        let receiver = s.spawn(move || {
+            let mut clock = wall_clock();
+            let first = host.receive(Duration::from_secs(5), &mut clock);
+            let second = host.receive(Duration::from_secs(5), &mut clock);
+            (first, second)
+        });
+
+        let mut clock = wall_clock();
+        assert!(!device.is_in_sync(&mut clock));
+
+        let ack = device
+            .send(Address::A, &[1], &mut clock)
+            .expect("not acknowledged");
+        assert_eq!(ack.payload(), &[5]);
+        assert!(device.is_in_sync(&mut clock));
+
+        let ack = device
+            .send(Address::A, &[2], &mut clock)
+            .expect("not acknowledged");
+        assert_eq!(ack.payload(), &[]);
+
+        let (first, second) = receiver.join().expect("host panicked");
+        assert_eq!(first.expect("nothing received").payload(), &[1]);
+        assert_eq!(second.expect("nothing received").payload(), &[2]);
+    });
+}
+
+#[test]
+fn gazell_device_finds_host_which_has_hopped_on() {
+    let air = Air::new();
+    let (a, b) = (air.radio(), air.radio());
+
+    let config = gazell_config();
+    let mut host =
+        Host::new(esb_radio(&b).into_receiver(), &[Address::A], config).expect("layout is ESB");
+    let mut device = Device::new(esb_radio(&a).into_transmitter(), config).expect("layout is ESB");
+
+    thread::scope(|s| {
+        let receiver = s.spawn(move || host.receive(Duration::from_secs(5), &mut wall_clock()));
+
+        wait_until_listening(&b);
+        assert_eq!(b.frequency(), Some(config.channels.channels()[0]));
+
+        // the host moves on to the next channel after its dwell time, before anything is sent
+        let started = Instant::now();
+        while b.frequency() != Some(config.channels.channels()[1]) {
+            assert!(
+                started.elapsed() < Duration::from_secs(1),
+                "host didn't hop"
+            );
+            thread::yield_now();
+        }
+
+        let mut clock = wall_clock();
+        let ack = device
+            .send(Address::A, &[3], &mut clock)
+            .expect("not acknowledged");
+        assert_eq!(ack.payload(), &[]);
+        assert!(device.is_in_sync(&mut clock));
+
+        let received = receiver
+            .join()
+            .expect("host panicked")
+            .expect("nothing received");
+        assert_eq!(received.payload(), &[3]);
+    });
+}
+
+#[test]
+fn gazell_device_without_host_gives_up() {
+    let air = Air::new();
+    let a = air.radio();
+
+    let config = gazell::Config {
+        timeslot_period: Duration::from_millis(1),
+        max_tx_attempts: 5,
+        ..gazell_config()
+    };
+    let mut device = Device::new(esb_radio(&a).into_transmitter(), config).expect("layout is ESB");
+
+    let mut clock = wall_clock();
+    let result = device.send(Address::A, &[1], &mut clock);
+    assert_eq!(result.unwrap_err(), Error::NotAcknowledged);
+    assert!(!device.is_in_sync(&mut clock));
+}
+
+#[test]
+fn advertisement_is_heard_on_channel_38() {
+    let air = Air::new();
+    let (a, b) = (air.radio(), air.radio());
+
+    let address = DeviceAddress {
+        base: 0x4433_2211,
+        prefix: 0x6655,
+        tx_add: false,
+    };
+    let advertiser = Advertiser::new(
+        Radio::new_zeroed(&a).into_transmitter(),
+        address,
+        PduType::NonConnectable,
+        &[2, 1, 6],
+        Duration::from_millis(100),
+    )
+    .expect("advertiser is valid");
+
+    let scanner = Radio::new_zeroed(&b).into_transmitter();
+    scanner
+        .apply_preset(Preset::Ble1M)
+        .and_then(|s| s.set_advertising_access_address(Address::A))
+        .expect("address fits");
+    scanner.set_advertising_channel(AdvertisingChannel::Ch38);
+    let scanner = scanner.disable();
+
+    thread::scope(|s| {
+        let receiver = s.spawn(move || {
+            let scanner = scanner.into_receiver();
+            scanner.enable_rx_address(Address::A);
+            scanner.receive_packet_with_timeout(1_000_000)
+        });
+
+        wait_until_listening(&b);
+        advertiser.advertise_event().expect("advertising failed");
+
+        let received = receiver
+            .join()
+            .expect("scanner panicked")
+            .expect("nothing received");
+        assert_eq!(received.packet().s0(), Some(0x02));
+        assert_eq!(
+            received.packet().payload(),
+            &[0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 2, 1, 6]
+        );
+    });
+}